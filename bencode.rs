@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+// Valor bencode (BEP-3): mensajes KRPC del DHT y archivos .torrent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    pub fn bytes(value: impl AsRef<[u8]>) -> Self {
+        Bencode::Bytes(value.as_ref().to_vec())
+    }
+
+    pub fn dict<K: AsRef<[u8]>>(entries: impl IntoIterator<Item = (K, Bencode)>) -> Self {
+        Bencode::Dict(entries.into_iter().map(|(k, v)| (k.as_ref().to_vec(), v)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(map) => map.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Bencode::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Bencode]> {
+        match self {
+            Bencode::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(n) => out.extend_from_slice(format!("i{}e", n).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            }
+            Bencode::List(items) => {
+                out.push(b'l');
+                items.iter().for_each(|item| item.encode_into(out));
+                out.push(b'e');
+            }
+            // BTreeMap ya da las claves ordenadas, como exige el formato
+            Bencode::Dict(map) => {
+                out.push(b'd');
+                for (key, value) in map {
+                    Bencode::Bytes(key.clone()).encode_into(out);
+                    value.encode_into(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+
+    // Decodifica un valor completo; basura al final es un error
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (value, rest) = decode_value(data)?;
+        rest.is_empty().then_some(value)
+    }
+}
+
+// Bytes originales del valor de `key` en un diccionario bencode completo, sin
+// decodificar y volver a codificar (que cambiaría un valor no canónico)
+pub fn raw_dict_value<'a>(data: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    if *data.first()? != b'd' {
+        return None;
+    }
+    let mut rest = &data[1..];
+    let mut found = None;
+    while *rest.first()? != b'e' {
+        let (Bencode::Bytes(k), value_start) = decode_value(rest)? else {
+            return None;
+        };
+        let (_, next) = decode_value(value_start)?;
+        if k == key.as_bytes() {
+            found = Some(&value_start[..value_start.len() - next.len()]);
+        }
+        rest = next;
+    }
+    // Como en decode, basura tras el diccionario invalida el archivo
+    if rest.len() != 1 {
+        return None;
+    }
+    found
+}
+
+fn decode_value(data: &[u8]) -> Option<(Bencode, &[u8])> {
+    match *data.first()? {
+        b'i' => {
+            let end = data.iter().position(|&b| b == b'e')?;
+            let n = std::str::from_utf8(&data[1..end]).ok()?.parse().ok()?;
+            Some((Bencode::Int(n), &data[end + 1..]))
+        }
+        b'l' => {
+            let mut items = Vec::new();
+            let mut rest = &data[1..];
+            while *rest.first()? != b'e' {
+                let (item, next) = decode_value(rest)?;
+                items.push(item);
+                rest = next;
+            }
+            Some((Bencode::List(items), &rest[1..]))
+        }
+        b'd' => {
+            let mut map = BTreeMap::new();
+            let mut rest = &data[1..];
+            while *rest.first()? != b'e' {
+                let (Bencode::Bytes(key), next) = decode_value(rest)? else {
+                    return None;
+                };
+                let (value, next) = decode_value(next)?;
+                map.insert(key, value);
+                rest = next;
+            }
+            Some((Bencode::Dict(map), &rest[1..]))
+        }
+        b'0'..=b'9' => {
+            let colon = data.iter().position(|&b| b == b':')?;
+            let len: usize = std::str::from_utf8(&data[..colon]).ok()?.parse().ok()?;
+            let start = colon + 1;
+            let bytes = data.get(start..start.checked_add(len)?)?;
+            Some((Bencode::Bytes(bytes.to_vec()), &data[start + len..]))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bencode_round_trip() {
+        let raw = b"d1:ad2:id20:abcdefghij01234567899:info_hash20:mnopqrstuvwxyz123456e1:q9:get_peers1:t2:aa1:y1:qe";
+        let value = Bencode::decode(raw).unwrap();
+
+        assert_eq!(value.get("q").and_then(Bencode::as_bytes), Some(&b"get_peers"[..]));
+        assert_eq!(value.get("a").and_then(|a| a.get("id")).and_then(Bencode::as_bytes).map(<[u8]>::len), Some(20));
+        assert_eq!(value.encode(), raw.to_vec());
+        assert_eq!(Bencode::decode(b"li-3e4:spame").unwrap(), Bencode::List(vec![Bencode::Int(-3), Bencode::bytes("spam")]));
+        assert_eq!(Bencode::decode(b"5:abc"), None);
+        assert_eq!(Bencode::decode(b"i1ei2e"), None);
+    }
+
+    #[test]
+    fn test_raw_dict_value_keeps_original_bytes() {
+        let raw = b"d4:infod4:name1:a6:lengthi1ee3:zzzi0ee";
+        assert_eq!(raw_dict_value(raw, "info"), Some(&b"d4:name1:a6:lengthi1ee"[..]));
+        assert_eq!(raw_dict_value(raw, "missing"), None);
+        assert_eq!(raw_dict_value(b"d4:infoi1eex", "info"), None);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use regex::{Regex, RegexBuilder};
+
+use super::Torrent;
+
+// Palabras que nunca deben acabar en los resultados ni en el CSV (grupos,
+// uploaders falsos...). Se comparan sin distinguir mayúsculas con el nombre y
+// el uploader.
+#[derive(Debug, Default)]
+pub struct Blocklist {
+    // Línea original del archivo (para el log) y su regex
+    patterns: Vec<(String, Regex)>,
+}
+
+impl Blocklist {
+    // Una entrada por línea ('#' para comentarios). Por defecto es una subcadena;
+    // con el prefijo "re:" es una expresión regular.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut patterns = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pattern = match line.strip_prefix("re:") {
+                Some(re) => re.trim().to_string(),
+                None => regex::escape(line),
+            };
+            let re = RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("invalid blocklist entry '{}': {}", line, e))?;
+            patterns.push((line.to_string(), re));
+        }
+        Ok(Self { patterns })
+    }
+
+    // Primera entrada que coincide con el texto
+    pub fn matches(&self, text: &str) -> Option<&str> {
+        self.patterns
+            .iter()
+            .find(|(_, re)| re.is_match(text))
+            .map(|(line, _)| line.as_str())
+    }
+
+    pub fn blocks(&self, torrent: &Torrent) -> Option<&str> {
+        self.matches(&torrent.name).or_else(|| self.matches(&torrent.uploader))
+    }
+
+    // Quita los torrents bloqueados, dejando constancia de cada uno en stderr
+    pub fn filter(&self, torrents: Vec<Torrent>) -> Vec<Torrent> {
+        torrents
+            .into_iter()
+            .filter(|torrent| match self.blocks(torrent) {
+                Some(entry) => {
+                    eprintln!("  - Blocked ('{}'): {}", entry, torrent.name);
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocklist_matches_substrings_and_regexes() {
+        let blocklist = Blocklist::parse("# grupos\nYIFY\n\nre:^fake[-_ ]\n").unwrap();
+
+        assert_eq!(blocklist.matches("Some.Movie.2024.1080p-yify"), Some("YIFY"));
+        assert_eq!(blocklist.matches("FAKE_Release"), Some("re:^fake[-_ ]"));
+        assert_eq!(blocklist.matches("Not a fake release"), None);
+        assert!(Blocklist::parse("re:(unclosed").is_err());
+        assert!(Blocklist::default().matches("anything").is_none());
+    }
+}
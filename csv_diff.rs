@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::Serialize;
+use crate::csv_writer::{read_all_records, TorrentCsvRecord};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SeederChange {
+    pub infohash: String,
+    pub name: String,
+    pub old: i32,
+    pub new: i32,
+}
+
+// Diferencias entre dos snapshots, por infohash y no por posición de línea
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CsvDiff {
+    // En el orden del snapshot nuevo
+    pub added: Vec<TorrentCsvRecord>,
+    // En el orden del snapshot viejo
+    pub removed: Vec<TorrentCsvRecord>,
+    // Solo los comunes cuyo número de seeders ha cambiado
+    pub seeders_changed: Vec<SeederChange>,
+}
+
+impl fmt::Display for CsvDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Added:          {}", self.added.len())?;
+        for record in &self.added {
+            writeln!(f, "  + {} {} ({} seeders)", record.infohash, record.name, record.seeders)?;
+        }
+        writeln!(f, "Removed:        {}", self.removed.len())?;
+        for record in &self.removed {
+            writeln!(f, "  - {} {}", record.infohash, record.name)?;
+        }
+        write!(f, "Seeders changed: {}", self.seeders_changed.len())?;
+        for change in &self.seeders_changed {
+            write!(f, "\n  ~ {} {} ({} -> {})", change.infohash, change.name, change.old, change.new)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn diff_records(old: Vec<TorrentCsvRecord>, new: Vec<TorrentCsvRecord>) -> CsvDiff {
+    let mut old_by_hash: HashMap<String, TorrentCsvRecord> =
+        old.iter().map(|record| (record.infohash.clone(), record.clone())).collect();
+    let mut diff = CsvDiff::default();
+
+    for record in new {
+        match old_by_hash.remove(&record.infohash) {
+            Some(previous) if previous.seeders != record.seeders => diff.seeders_changed.push(SeederChange {
+                infohash: record.infohash,
+                name: record.name,
+                old: previous.seeders,
+                new: record.seeders,
+            }),
+            Some(_) => {}
+            None => diff.added.push(record),
+        }
+    }
+    // Lo que no ha aparecido en el nuevo se ha eliminado; se recorre `old` para
+    // conservar su orden
+    diff.removed = old.into_iter().filter(|r| old_by_hash.remove(&r.infohash).is_some()).collect();
+
+    diff
+}
+
+// Compara dos CSV (o .csv.gz) por infohash. Las columnas se leen por el nombre
+// de la cabecera, así que los dos archivos pueden venir de versiones distintas.
+pub fn diff_csv(old_path: &str, new_path: &str, sep: char) -> std::io::Result<CsvDiff> {
+    Ok(diff_records(read_all_records(old_path, sep)?, read_all_records(new_path, sep)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_writer::CSV_HEADER;
+
+    #[test]
+    fn test_diff_csv() {
+        let row = |c: &str, seeders: i32| format!("{};{};1;0;{};0;0;0;1;1337x", c.repeat(40), c, seeders);
+        let old_path = std::env::temp_dir().join(format!("diff_old_{}.csv", std::process::id()));
+        let new_path = std::env::temp_dir().join(format!("diff_new_{}.csv", std::process::id()));
+        std::fs::write(&old_path, format!("{}\n{}\n{}\n{}\n", CSV_HEADER, row("a", 5), row("b", 9), row("c", 1))).unwrap();
+        // Otro orden de filas no cuenta como cambio
+        std::fs::write(&new_path, format!("{}\n{}\n{}\n{}\n", CSV_HEADER, row("d", 3), row("c", 1), row("a", 12))).unwrap();
+
+        let diff = diff_csv(old_path.to_str().unwrap(), new_path.to_str().unwrap(), ';').unwrap();
+        let _ = std::fs::remove_file(&old_path);
+        let _ = std::fs::remove_file(&new_path);
+
+        assert_eq!(diff.added.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["d"]);
+        assert_eq!(diff.removed.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["b"]);
+        assert_eq!(
+            diff.seeders_changed,
+            vec![SeederChange { infohash: "a".repeat(40), name: "a".to_string(), old: 5, new: 12 }]
+        );
+        assert!(diff.to_string().contains("Seeders changed: 1"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+// Los CSV terminados en .gz se leen y escriben comprimidos de forma transparente
+pub fn is_gzip_path(path: impl AsRef<Path>) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "gz")
+}
+
+// MultiGzDecoder para leer también los archivos a los que se han ido añadiendo
+// miembros gzip (ver append_csv_lines)
+pub fn open_csv_reader(path: impl AsRef<Path>) -> std::io::Result<Box<dyn BufRead>> {
+    let file = File::open(&path)?;
+    if is_gzip_path(&path) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+pub fn read_csv_to_string(path: impl AsRef<Path>) -> std::io::Result<String> {
+    let mut content = String::new();
+    open_csv_reader(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn write_content(file: File, content: &[u8], gzip: bool) -> std::io::Result<()> {
+    if gzip {
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        encoder.write_all(content)?;
+        encoder.finish()?.flush()
+    } else {
+        let mut writer = BufWriter::new(file);
+        writer.write_all(content)?;
+        writer.flush()
+    }
+}
+
+pub(crate) fn write_bytes(path: impl AsRef<Path>, content: &[u8], gzip: bool) -> std::io::Result<()> {
+    write_content(File::create(path)?, content, gzip)
+}
+
+// Todos los CSV acaban en exactamente un salto de línea, lo escriba quien lo
+// escriba, para que git diff no muestre ruido de "No newline at end of file"
+pub fn csv_text<S: AsRef<str>>(lines: &[S]) -> String {
+    let mut content = String::new();
+    for line in lines {
+        content.push_str(line.as_ref());
+        content.push('\n');
+    }
+    with_single_trailing_newline(&content)
+}
+
+fn with_single_trailing_newline(content: &str) -> String {
+    let trimmed = content.trim_end_matches(['\n', '\r']);
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", trimmed)
+    }
+}
+
+// Un archivo sin salto de línea final (editado a mano o por otra herramienta)
+// pegaría la primera línea añadida a su última fila
+pub(crate) fn missing_final_newline(path: impl AsRef<Path>) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1)).is_ok() && file.read_exact(&mut last).is_ok() && last[0] != b'\n'
+}
+
+pub(crate) async fn missing_final_newline_async(path: impl AsRef<Path>) -> bool {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    
+    let Ok(mut file) = tokio::fs::File::open(path).await else {
+        return false;
+    };
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1)).await.is_ok() && file.read_exact(&mut last).await.is_ok() && last[0] != b'\n'
+}
+
+// Sobrescribe el archivo completo, comprimido si termina en .gz
+pub fn write_csv_file(path: impl AsRef<Path>, content: &str) -> std::io::Result<()> {
+    let gzip = is_gzip_path(&path);
+    write_bytes(path, with_single_trailing_newline(content).as_bytes(), gzip)
+}
+
+// Añade líneas al final. En un .gz cada llamada añade un miembro gzip nuevo,
+// que es válido y se lee sin problemas con MultiGzDecoder.
+pub fn append_csv_lines(path: impl AsRef<Path>, lines: &[String]) -> std::io::Result<()> {
+    let gzip = is_gzip_path(&path);
+    let needs_newline = !gzip && missing_final_newline(&path);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let mut content = if needs_newline { "\n".to_string() } else { String::new() };
+    for line in lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    write_content(file, content.as_bytes(), gzip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gzip_csv_round_trip_with_appends() {
+        let path = std::env::temp_dir().join(format!("csv_io_{}.csv.gz", std::process::id()));
+
+        write_csv_file(&path, "header\n").unwrap();
+        append_csv_lines(&path, &["a".to_string(), "b".to_string()]).unwrap();
+        append_csv_lines(&path, &["c".to_string()]).unwrap();
+
+        let raw = std::fs::read(&path).unwrap();
+        let content = read_csv_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(&raw[..2], &[0x1f, 0x8b]);
+        assert_eq!(content, "header\na\nb\nc\n");
+        assert!(!is_gzip_path("torrents_part_1.csv"));
+    }
+
+    #[test]
+    fn test_single_trailing_newline() {
+        let path = std::env::temp_dir().join(format!("csv_io_newline_{}.csv", std::process::id()));
+
+        write_csv_file(&path, "header\na\n\n\n").unwrap();
+        let rewritten = std::fs::read_to_string(&path).unwrap();
+        // Escrito por otra herramienta, sin salto final
+        std::fs::write(&path, "header\na").unwrap();
+        append_csv_lines(&path, &["b".to_string()]).unwrap();
+        let appended = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(rewritten, "header\na\n");
+        assert_eq!(appended, "header\na\nb\n");
+        assert_eq!(csv_text(&["header", "a"]), "header\na\n");
+        assert_eq!(csv_text(&["header", "a", ""]), "header\na\n");
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use crate::csv_io::{csv_text, is_gzip_path, read_csv_to_string, write_bytes};
+use crate::csv_writer::{join_csv_fields, normalize_infohash, split_csv_line, validate_csv_header, CSV_COLUMNS};
+
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    // Filas de datos revisadas (sin contar la cabecera)
+    pub total: usize,
+    // Números de línea (1 = cabecera) que se han reconstruido
+    pub repaired: Vec<usize>,
+    // Números de línea que no se pudieron arreglar; van al archivo .rejected
+    pub unrepairable: Vec<usize>,
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = |numbers: &[usize]| {
+            numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")
+        };
+
+        writeln!(f, "Rows checked:   {}", self.total)?;
+        writeln!(f, "Repaired:       {}", self.repaired.len())?;
+        write!(f, "Unrepairable:   {}", self.unrepairable.len())?;
+        if !self.unrepairable.is_empty() {
+            write!(f, " (lines {})", lines(&self.unrepairable))?;
+        }
+        Ok(())
+    }
+}
+
+// Columnas numéricas tras el nombre: size_bytes, created_unix, seeders, leechers,
+// completed (puede ir vacío) y scraped_date
+fn numeric_tail_is_valid(fields: &[String]) -> bool {
+    fields[2..CSV_COLUMNS.len()].iter().enumerate().all(|(i, field)| {
+        let is_completed = i + 2 == 6;
+        (is_completed && field.is_empty()) || field.parse::<i64>().is_ok()
+    })
+}
+
+// Intenta arreglar una fila. Con columnas de más, lo habitual es un separador sin
+// escapar en el nombre: se vuelven a unir los campos sobrantes en la posición 1.
+fn repair_fields(mut fields: Vec<String>, columns: usize, sep: char) -> Option<Vec<String>> {
+    if fields.len() < CSV_COLUMNS.len() {
+        return None;
+    }
+
+    if fields.len() > columns {
+        let extra = fields.len() - columns;
+        let name_parts: Vec<String> = fields.drain(1..=1 + extra).collect();
+        fields.insert(1, name_parts.join(&sep.to_string()));
+    }
+
+    fields[0] = normalize_infohash(&fields[0])?;
+    numeric_tail_is_valid(&fields).then_some(fields)
+}
+
+// Revisa un CSV completo y lo reescribe limpio. Las filas irreparables se
+// quitan del archivo pero se guardan en `<path>.rejected` para no perderlas.
+pub fn repair_csv(path: &str, sep: char) -> std::io::Result<RepairReport> {
+    let content = read_csv_to_string(path)?;
+    let mut lines = content.lines();
+    let Some(header) = lines.next() else {
+        return Ok(RepairReport::default());
+    };
+    validate_csv_header(header, sep)?;
+    let columns = header.split(sep).count();
+
+    let mut report = RepairReport::default();
+    let mut cleaned = vec![header.to_string()];
+    let mut rejected = Vec::new();
+
+    for (i, line) in lines.enumerate() {
+        let line_no = i + 2;
+        if line.trim().is_empty() {
+            continue;
+        }
+        report.total += 1;
+
+        let fields = split_csv_line(line, sep);
+        let well_formed = (CSV_COLUMNS.len()..=columns).contains(&fields.len());
+        match repair_fields(fields, columns, sep) {
+            Some(fields) => {
+                let repaired = join_csv_fields(&fields, sep);
+                if !well_formed || repaired != line {
+                    report.repaired.push(line_no);
+                }
+                cleaned.push(repaired);
+            }
+            None => {
+                report.unrepairable.push(line_no);
+                rejected.push(line.to_string());
+            }
+        }
+    }
+
+    // Escribir en un temporal y renombrar, para no dejar el archivo a medias
+    let tmp_path = format!("{}.tmp", path);
+    write_bytes(&tmp_path, csv_text(&cleaned).as_bytes(), is_gzip_path(path))?;
+    fs::rename(&tmp_path, path)?;
+
+    if !rejected.is_empty() {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(format!("{}.rejected", path))?;
+        for line in &rejected {
+            writeln!(file, "{}", line)?;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_writer::CSV_HEADER;
+
+    #[test]
+    fn test_repair_csv() {
+        let path = std::env::temp_dir().join(format!("repair_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let hash = "2c6b6858d61da9543d4231a71db4b1c9264b0685";
+        let rows = [
+            CSV_HEADER.to_string(),
+            format!("{};Good name;1024;1700000000;10;2;5;1700000100;3;1337x", hash),
+            format!("{};Bad; name; here;1024;1700000000;10;2;5;1700000100;3;1337x", hash.to_uppercase()),
+            format!("{};Legacy row;1024;1700000000;10;2;5;1700000100", hash),
+            "not-a-hash;Name;1024;1700000000;10;2;5;1700000100;3".to_string(),
+            format!("{};too;short", hash),
+        ];
+        fs::write(path, rows.join("\n") + "\n").unwrap();
+
+        let report = repair_csv(path, ';').unwrap();
+        let cleaned = fs::read_to_string(path).unwrap();
+        let rejected = fs::read_to_string(format!("{}.rejected", path)).unwrap();
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(format!("{}.rejected", path));
+
+        assert_eq!(report.total, 5);
+        assert_eq!(report.repaired, vec![3]);
+        assert_eq!(report.unrepairable, vec![5, 6]);
+
+        let lines: Vec<&str> = cleaned.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], format!("{};\"Bad; name; here\";1024;1700000000;10;2;5;1700000100;3;1337x", hash));
+        assert_eq!(lines[3], rows[3]);
+        assert_eq!(rejected.lines().count(), 2);
+    }
+}
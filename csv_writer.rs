@@ -1,10 +1,17 @@
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufRead, Write};
 use std::path::Path;
-use std::collections::HashSet;
-use chrono::Utc;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use chrono::{DateTime, Utc};
+use crate::csv_io::{
+    append_csv_lines, csv_text, is_gzip_path, missing_final_newline_async, open_csv_reader, read_csv_to_string, write_csv_file,
+};
+use crate::torrent_search::convert_bytes;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TorrentCsvRecord {
     pub infohash: String,
     pub name: String,
@@ -14,26 +21,86 @@ pub struct TorrentCsvRecord {
     pub leechers: i32,
     pub completed: i32,
     pub scraped_date: i64,
+    pub files_count: u32,
+    // Proveedor de origen; vacío en filas escritas antes de existir la columna
+    #[serde(default)]
+    pub source: String,
 }
 
 impl TorrentCsvRecord {
-    pub fn to_csv_line(&self) -> String {
-        format!(
-            "{};{};{};{};{};{};{};{}",
-            self.infohash,
-            self.name.replace(";", ","), // Escapar punto y coma
-            self.size_bytes,
-            self.created_unix,
-            self.seeders,
-            self.leechers,
-            self.completed,
-            self.scraped_date
-        )
+    pub fn to_csv_line(&self, sep: char) -> String {
+        join_csv_fields(&self.csv_fields(), sep)
+    }
+    
+    // Recorta la fila a `columns` columnas (nunca menos de las obligatorias),
+    // para añadir a archivos con una cabecera anterior sin desalinearlos
+    pub fn to_csv_line_with_columns(&self, sep: char, columns: usize) -> String {
+        let fields = self.csv_fields();
+        let columns = columns.clamp(CSV_COLUMNS.len(), fields.len());
+        join_csv_fields(&fields[..columns], sep)
+    }
+    
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.infohash.clone(),
+            self.name.clone(),
+            self.size_bytes.to_string(),
+            self.created_unix.to_string(),
+            self.seeders.to_string(),
+            self.leechers.to_string(),
+            self.completed.to_string(),
+            self.scraped_date.to_string(),
+            self.files_count.to_string(),
+            self.source.clone(),
+        ]
+    }
+    
+    // Acepta filas de 8 columnas (formato antiguo), con files_count o con source
+    pub fn from_csv_line(line: &str, sep: char) -> Option<Self> {
+        Self::from_fields(&split_csv_line(line, sep), &ColumnLayout::positional())
+    }
+    
+    // Las columnas obligatorias que el archivo tiene deben estar en la fila;
+    // las que le faltan al archivo quedan con su valor por defecto
+    fn from_fields(parts: &[String], layout: &ColumnLayout) -> Option<Self> {
+        let field = |column: usize| layout.0[column].and_then(|i| parts.get(i));
+        if (0..CSV_COLUMNS.len()).any(|column| layout.0[column].is_some() && field(column).is_none()) {
+            return None;
+        }
+        fn parse<T: std::str::FromStr + Default>(value: Option<&String>) -> T {
+            value.and_then(|v| v.parse().ok()).unwrap_or_default()
+        }
+        
+        Some(Self {
+            infohash: field(0).map(|v| v.to_lowercase()).unwrap_or_default(),
+            name: field(1).cloned().unwrap_or_default(),
+            size_bytes: parse(field(2)),
+            created_unix: parse(field(3)),
+            seeders: parse(field(4)),
+            leechers: parse(field(5)),
+            completed: parse(field(6)),
+            scraped_date: parse(field(7)),
+            files_count: parse(field(8)),
+            source: field(9).cloned().unwrap_or_default(),
+        })
+    }
+    
+    // Popularidad combinada; es la columna opcional `peers` si se guarda
+    pub fn peers(&self) -> u64 {
+        self.seeders.max(0) as u64 + self.leechers.max(0) as u64
+    }
+    
+    // Sin consultar nada más: solo si el torrent ya trae un infohash válido
+    pub fn try_from_torrent(torrent: &super::Torrent) -> Option<Self> {
+        let infohash = torrent.infohash.as_deref().and_then(normalize_infohash)?;
+        Some(Self::from_torrent(torrent, &infohash))
     }
     
+    // Si el torrent ya trae su infohash se usa ese en lugar del recibido
     pub fn from_torrent(torrent: &super::Torrent, infohash: &str) -> Self {
+        let infohash = torrent.infohash.as_deref().unwrap_or(infohash);
         Self {
-            infohash: infohash.to_string(),
+            infohash: normalize_infohash(infohash).unwrap_or_else(|| infohash.to_lowercase()),
             name: torrent.name.clone(),
             size_bytes: parse_size_to_bytes(&torrent.size),
             created_unix: torrent.date.unwrap_or_else(|| Utc::now().timestamp()),
@@ -41,10 +108,27 @@ impl TorrentCsvRecord {
             leechers: torrent.leechers,
             completed: 0,
             scraped_date: Utc::now().timestamp(),
+            files_count: torrent.files_count.unwrap_or(0),
+            source: torrent.source.clone(),
         }
     }
 }
 
+// Mirrors localizados escriben "1,5 GB". Una sola coma sin punto es decimal,
+// salvo que le sigan exactamente tres cifras ("1,024 MB"): entonces, como
+// cualquier grupo de miles, se quitan las comas.
+pub(crate) fn normalize_decimal(number: &str) -> String {
+    let thousands = number
+        .split(',')
+        .skip(1)
+        .all(|group| group.split('.').next().is_some_and(|digits| digits.len() == 3));
+    if number.matches(',').count() == 1 && !number.contains('.') && !thousands {
+        number.replace(',', ".")
+    } else {
+        number.replace(',', "")
+    }
+}
+
 pub fn parse_size_to_bytes(size: &str) -> u64 {
     let size = size.trim();
     let parts: Vec<&str> = size.split_whitespace().collect();
@@ -53,7 +137,7 @@ pub fn parse_size_to_bytes(size: &str) -> u64 {
         return 0;
     }
     
-    let number: f64 = parts[0].parse().unwrap_or(0.0);
+    let number: f64 = normalize_decimal(parts[0]).parse().unwrap_or(0.0);
     let unit = parts[1].to_uppercase();
     
     let multiplier = match unit.as_str() {
@@ -68,34 +152,373 @@ pub fn parse_size_to_bytes(size: &str) -> u64 {
     (number * multiplier) as u64
 }
 
-pub fn read_existing_infohashes(csv_path: &str) -> HashSet<String> {
+// Columnas obligatorias, en orden. Las posteriores (files_count, ...) son opcionales.
+pub const CSV_COLUMNS: [&str; 8] = [
+    "infohash",
+    "name",
+    "size_bytes",
+    "created_unix",
+    "seeders",
+    "leechers",
+    "completed",
+    "scraped_date",
+];
+
+pub const CSV_HEADER: &str = "infohash;name;size_bytes;created_unix;seeders;leechers;completed;scraped_date;files_count;source";
+
+pub const DEFAULT_CSV_SEP: char = ';';
+
+// CSV_SEP: separador de campos, un único carácter ("\t" o "tab" para TSV)
+pub fn csv_separator() -> char {
+    let Ok(value) = std::env::var("CSV_SEP") else {
+        return DEFAULT_CSV_SEP;
+    };
+    
+    let mut chars = value.chars();
+    match (value.as_str(), chars.next(), chars.next()) {
+        ("\\t" | "tab", _, _) => '\t',
+        (_, Some(sep), None) if sep != '"' && sep != '\n' && sep != '\r' => sep,
+        _ => {
+            eprintln!("Warning: invalid CSV_SEP '{}', using '{}'", value, DEFAULT_CSV_SEP);
+            DEFAULT_CSV_SEP
+        }
+    }
+}
+
+pub fn csv_header(sep: char) -> String {
+    CSV_HEADER.replace(DEFAULT_CSV_SEP, &sep.to_string())
+}
+
+// Divide una línea respetando campos entre comillas ("a;b", con "" como comilla literal)
+pub fn split_csv_line(line: &str, sep: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            c if c == sep && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    
+    fields
+}
+
+// Une los campos entrecomillando los que contienen el separador o comillas
+pub fn join_csv_fields<S: AsRef<str>>(fields: &[S], sep: char) -> String {
+    let mut line = String::new();
+    
+    for (i, field) in fields.iter().enumerate() {
+        let field = field.as_ref();
+        if i > 0 {
+            line.push(sep);
+        }
+        if field.contains(sep) || field.contains('"') || field.contains('\n') {
+            line.push('"');
+            line.push_str(&field.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(field);
+        }
+    }
+    
+    line
+}
+
+pub fn validate_csv_header(header: &str, sep: char) -> std::io::Result<()> {
+    let columns: Vec<&str> = header.trim_end().split(sep).collect();
+    
+    if columns.len() < CSV_COLUMNS.len() || columns[..CSV_COLUMNS.len()] != CSV_COLUMNS {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unexpected CSV header '{}', expected '{}'", header, csv_header(sep)),
+        ));
+    }
+    
+    Ok(())
+}
+
+// Devuelve los infohashes existentes y el número de filas mal formadas que se saltaron
+pub fn read_existing_infohashes(csv_path: &str, sep: char) -> std::io::Result<(HashSet<String>, usize)> {
     let mut infohashes = HashSet::new();
+    let mut skipped = 0;
+    
+    let reader = match open_csv_reader(csv_path) {
+        Ok(reader) => reader,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((infohashes, skipped)),
+        Err(e) => return Err(e),
+    };
+    
+    for (i, line) in reader.lines().enumerate() {
+        collect_infohash(&line?, i, csv_path, sep, &mut infohashes, &mut skipped)?;
+    }
+    
+    Ok((infohashes, skipped))
+}
+
+// Procesa la línea `i` (0 = cabecera) de un CSV para read_existing_infohashes
+fn collect_infohash(
+    line: &str,
+    i: usize,
+    csv_path: &str,
+    sep: char,
+    infohashes: &mut HashSet<String>,
+    skipped: &mut usize,
+) -> std::io::Result<()> {
+    if i == 0 {
+        return validate_csv_header(line, sep);
+    }
     
-    if let Ok(file) = File::open(csv_path) {
-        let reader = BufReader::new(file);
+    let fields = split_csv_line(line, sep);
+    if fields.len() < CSV_COLUMNS.len() {
+        eprintln!("Skipping malformed row {} in {}", i + 1, csv_path);
+        *skipped += 1;
+        return Ok(());
+    }
+    
+    if let Some(infohash) = fields.into_iter().next() {
+        infohashes.insert(infohash.to_lowercase());
+    }
+    Ok(())
+}
+
+// Igual que read_existing_infohashes pero sin bloquear el runtime de tokio
+pub async fn read_existing_infohashes_async(csv_path: &str, sep: char) -> std::io::Result<(HashSet<String>, usize)> {
+    if is_gzip_path(csv_path) {
+        let path = csv_path.to_string();
+        return tokio::task::spawn_blocking(move || read_existing_infohashes(&path, sep))
+            .await
+            .map_err(std::io::Error::other)?;
+    }
+    
+    let mut infohashes = HashSet::new();
+    let mut skipped = 0;
+    
+    let content = match tokio::fs::read_to_string(csv_path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((infohashes, skipped)),
+        Err(e) => return Err(e),
+    };
+    
+    for (i, line) in content.lines().enumerate() {
+        collect_infohash(line, i, csv_path, sep, &mut infohashes, &mut skipped)?;
+    }
+    
+    Ok((infohashes, skipped))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CsvStats {
+    pub total: usize,
+    pub alive: usize,
+    pub dead: usize,
+    pub total_size_bytes: u64,
+    pub median_seeders: f64,
+    pub max_seeders: i32,
+    pub oldest_scraped: Option<i64>,
+    pub newest_scraped: Option<i64>,
+}
+
+impl fmt::Display for CsvStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = |ts: Option<i64>| {
+            ts.and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
         
-        for (i, line) in reader.lines().enumerate() {
-            if i == 0 {
-                continue; // Skip header
-            }
+        writeln!(f, "Torrents:       {}", self.total)?;
+        writeln!(f, "Alive / Dead:   {} / {}", self.alive, self.dead)?;
+        writeln!(f, "Total size:     {}", super::convert_bytes(self.total_size_bytes as f64))?;
+        writeln!(f, "Seeders:        median {:.1}, max {}", self.median_seeders, self.max_seeders)?;
+        write!(f, "Scraped:        {} .. {}", date(self.oldest_scraped), date(self.newest_scraped))
+    }
+}
+
+pub fn csv_stats(csv_path: &str, sep: char) -> CsvStats {
+    let mut stats = CsvStats::default();
+    let mut seeders = Vec::new();
+    
+    if let Ok(reader) = open_csv_reader(csv_path) {
+        for line in reader.lines().skip(1).map_while(Result::ok) {
+            let Some(record) = TorrentCsvRecord::from_csv_line(&line, sep) else {
+                continue;
+            };
             
-            if let Ok(line) = line {
-                if let Some(infohash) = line.split(';').next() {
-                    infohashes.insert(infohash.to_string());
-                }
+            stats.total += 1;
+            if record.seeders > 0 || record.leechers > 0 {
+                stats.alive += 1;
+            } else {
+                stats.dead += 1;
             }
+            stats.total_size_bytes += record.size_bytes;
+            stats.max_seeders = stats.max_seeders.max(record.seeders);
+            stats.oldest_scraped = Some(stats.oldest_scraped.map_or(record.scraped_date, |d| d.min(record.scraped_date)));
+            stats.newest_scraped = Some(stats.newest_scraped.map_or(record.scraped_date, |d| d.max(record.scraped_date)));
+            seeders.push(record.seeders);
         }
     }
     
-    infohashes
+    if !seeders.is_empty() {
+        seeders.sort_unstable();
+        let mid = seeders.len() / 2;
+        stats.median_seeders = if seeders.len() % 2 == 0 {
+            (seeders[mid - 1] as f64 + seeders[mid] as f64) / 2.0
+        } else {
+            seeders[mid] as f64
+        };
+    }
+    
+    stats
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwarmTotals {
+    pub seeders: u64,
+    pub leechers: u64,
+}
+
+impl fmt::Display for SwarmTotals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Swarm: {} peers ({} seeders, {} leechers)",
+            self.seeders + self.leechers,
+            self.seeders,
+            self.leechers
+        )
+    }
+}
+
+// Suma de seeders y leechers de los torrents vivos. Lee el archivo línea a
+// línea y se salta las filas mal formadas.
+pub fn total_swarm(csv_path: &str, sep: char) -> SwarmTotals {
+    let mut totals = SwarmTotals::default();
+    
+    if let Ok(reader) = open_csv_reader(csv_path) {
+        for line in reader.lines().skip(1).map_while(Result::ok) {
+            let Some(record) = TorrentCsvRecord::from_csv_line(&line, sep) else {
+                continue;
+            };
+            if record.seeders > 0 || record.leechers > 0 {
+                totals.seeders += record.seeders.max(0) as u64;
+                totals.leechers += record.leechers.max(0) as u64;
+            }
+        }
+    }
+    
+    totals
+}
+
+// Entrada del heap de top_torrents: a igualdad de clave (seeders, fecha...)
+// gana la fila que aparece antes en el archivo
+struct TopEntry {
+    key: i64,
+    line: usize,
+    record: TorrentCsvRecord,
+}
+
+impl TopEntry {
+    fn rank(&self) -> (i64, Reverse<usize>) {
+        (self.key, Reverse(self.line))
+    }
+}
+
+impl PartialEq for TopEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank() == other.rank()
+    }
+}
+
+impl Eq for TopEntry {}
+
+impl PartialOrd for TopEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TopEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+// Los `n` torrents con más seeders, de mayor a menor. Lee el archivo en
+// streaming con un min-heap de tamaño `n`, sin cargarlo entero en memoria.
+pub fn top_torrents(csv_path: &str, n: usize, sep: char) -> Vec<TorrentCsvRecord> {
+    top_torrents_by(csv_path, n, sep, |record| record.seeders as i64)
+}
+
+// Los `n` añadidos más recientemente (created_unix), del más nuevo al más viejo
+pub fn recent_torrents(csv_path: &str, n: usize, sep: char) -> Vec<TorrentCsvRecord> {
+    top_torrents_by(csv_path, n, sep, |record| record.created_unix)
+}
+
+fn top_torrents_by(
+    csv_path: &str,
+    n: usize,
+    sep: char,
+    key: impl Fn(&TorrentCsvRecord) -> i64,
+) -> Vec<TorrentCsvRecord> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    
+    if let Ok(reader) = open_csv_reader(csv_path) {
+        for (line, text) in reader.lines().skip(1).map_while(Result::ok).enumerate() {
+            let Some(record) = TorrentCsvRecord::from_csv_line(&text, sep) else {
+                continue;
+            };
+            heap.push(Reverse(TopEntry { key: key(&record), line, record }));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+    }
+    
+    // into_sorted_vec ordena de menor a mayor Reverse, es decir, de mayor a menor clave
+    heap.into_sorted_vec().into_iter().map(|Reverse(entry)| entry.record).collect()
+}
+
+// Columnas de la cabecera si el archivo ya existe y tiene una
+fn header_columns(header: &str, sep: char) -> Option<usize> {
+    let header = header.trim_end();
+    (!header.is_empty()).then(|| split_csv_line(header, sep).len())
+}
+
+fn existing_header_columns(csv_path: &str, sep: char) -> Option<usize> {
+    let mut header = String::new();
+    open_csv_reader(csv_path).ok()?.read_line(&mut header).ok()?;
+    header_columns(&header, sep)
+}
+
+async fn existing_header_columns_async(csv_path: &str, sep: char) -> Option<usize> {
+    use tokio::io::AsyncBufReadExt;
+    
+    let file = tokio::fs::File::open(csv_path).await.ok()?;
+    let mut header = String::new();
+    tokio::io::BufReader::new(file).read_line(&mut header).await.ok()?;
+    header_columns(&header, sep)
 }
 
 pub fn append_torrents_to_csv(
     csv_path: &str,
-    records: Vec<TorrentCsvRecord>
+    records: Vec<TorrentCsvRecord>,
+    sep: char,
 ) -> std::io::Result<usize> {
     // Leer infohashes existentes para evitar duplicados
-    let existing = read_existing_infohashes(csv_path);
+    let (existing, _) = read_existing_infohashes(csv_path, sep)?;
     
     // Filtrar registros nuevos
     let new_records: Vec<_> = records.into_iter()
@@ -106,35 +529,398 @@ pub fn append_torrents_to_csv(
         return Ok(0);
     }
     
-    // Abrir archivo en modo append
+    let columns = existing_header_columns(csv_path, sep).unwrap_or(usize::MAX);
+    let lines: Vec<String> = new_records.iter().map(|r| r.to_csv_line_with_columns(sep, columns)).collect();
+    append_csv_lines(csv_path, &lines)?;
+    
+    Ok(new_records.len())
+}
+
+// Versión async de append_torrents_to_csv: lectura con tokio::fs y una sola
+// escritura con todas las líneas nuevas
+pub async fn append_torrents_to_csv_async(
+    csv_path: &str,
+    records: Vec<TorrentCsvRecord>,
+    sep: char,
+) -> std::io::Result<usize> {
+    use tokio::io::AsyncWriteExt;
+    
+    // El .gz necesita el codificador síncrono de flate2
+    if is_gzip_path(csv_path) {
+        let path = csv_path.to_string();
+        return tokio::task::spawn_blocking(move || append_torrents_to_csv(&path, records, sep))
+            .await
+            .map_err(std::io::Error::other)?;
+    }
+    
+    let (existing, _) = read_existing_infohashes_async(csv_path, sep).await?;
+    let columns = existing_header_columns_async(csv_path, sep).await.unwrap_or(usize::MAX);
+    
+    let mut seen = HashSet::new();
+    let mut buffer = if missing_final_newline_async(csv_path).await { "\n".to_string() } else { String::new() };
+    for record in &records {
+        if existing.contains(&record.infohash) || !seen.insert(record.infohash.as_str()) {
+            continue;
+        }
+        buffer.push_str(&record.to_csv_line_with_columns(sep, columns));
+        buffer.push('\n');
+    }
+    
+    if seen.is_empty() {
+        return Ok(0);
+    }
+    
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(csv_path)
+        .await?;
+    file.write_all(buffer.as_bytes()).await?;
+    file.flush().await?;
+    
+    Ok(seen.len())
+}
+
+// Como append_torrents_to_csv, pero las filas que ya existen se actualizan
+// (seeders, leechers y scraped_date) en lugar de saltarse. Reescribe el archivo
+// entero, así que es más lento. Devuelve (actualizadas, añadidas).
+pub fn upsert_torrents_to_csv(
+    csv_path: &str,
+    records: Vec<TorrentCsvRecord>,
+    sep: char,
+) -> std::io::Result<(usize, usize)> {
+    let content = read_csv_to_string(csv_path)?;
+    let mut lines = content.lines();
+    let header = lines.next().unwrap_or("").to_string();
+    let columns = header_columns(&header, sep).unwrap_or(usize::MAX);
+    // La columna opcional peers (WRITE_PEERS del scraper) se mantiene al día
+    let peers_column = split_csv_line(&header, sep).iter().position(|c| c == "peers");
+    
+    // Si el mismo infohash llega repetido, gana el último
+    let mut pending: HashMap<&str, &TorrentCsvRecord> = records.iter().map(|r| (r.infohash.as_str(), r)).collect();
+    let mut output = vec![header];
+    let mut updated = 0;
+    for line in lines {
+        let mut fields = split_csv_line(line, sep);
+        // Una fila corta se deja tal cual y el registro se añade al final
+        let record = match fields.first() {
+            Some(hash) if fields.len() >= CSV_COLUMNS.len() => pending.remove(hash.to_lowercase().as_str()),
+            _ => None,
+        };
+        match record {
+            Some(record) => {
+                fields[4] = record.seeders.to_string();
+                fields[5] = record.leechers.to_string();
+                fields[7] = record.scraped_date.to_string();
+                if let Some(column) = peers_column {
+                    fields.resize(fields.len().max(column + 1), String::new());
+                    fields[column] = record.peers().to_string();
+                }
+                output.push(join_csv_fields(&fields, sep));
+                updated += 1;
+            }
+            _ => output.push(line.to_string()),
+        }
+    }
+    
+    // Lo que queda es nuevo; se añade en el orden de entrada
+    let mut appended = 0;
+    for record in &records {
+        if let Some(record) = pending.remove(record.infohash.as_str()) {
+            output.push(record.to_csv_line_with_columns(sep, columns));
+            appended += 1;
+        }
+    }
+    
+    write_csv_file(csv_path, &csv_text(&output))?;
+    Ok((updated, appended))
+}
+
+// Copia a `dst` (con cabecera) las filas de `src` con al menos `min_seeders`.
+// Las filas se copian tal cual y `src` no se modifica. Devuelve cuántas se copiaron.
+pub fn export_alive(src: &str, dst: &str, min_seeders: i32, sep: char) -> std::io::Result<usize> {
+    let mut lines = open_csv_reader(src)?.lines();
+    let header = lines.next().transpose()?.unwrap_or_else(|| csv_header(sep));
+    validate_csv_header(&header, sep)?;
+    
+    let mut out = format!("{}\n", header);
+    
+    let mut exported = 0;
+    for line in lines {
+        let line = line?;
+        let alive = TorrentCsvRecord::from_csv_line(&line, sep)
+            .is_some_and(|record| record.seeders >= min_seeders);
+        if alive {
+            out.push_str(&line);
+            out.push('\n');
+            exported += 1;
+        }
+    }
+    write_csv_file(dst, &out)?;
+    
+    Ok(exported)
+}
+
+// Copia `src` a `dst` con una columna size_human ("3.7 GB") calculada desde
+// size_bytes, para informes. Si ya la tenía se recalcula. `src` no se modifica.
+pub fn export_with_size_human(src: &str, dst: &str, sep: char) -> std::io::Result<usize> {
+    let mut lines = open_csv_reader(src)?.lines();
+    let header = lines.next().transpose()?.unwrap_or_else(|| csv_header(sep));
+    validate_csv_header(&header, sep)?;
+    
+    let mut columns = split_csv_line(header.trim_end(), sep);
+    let size_column = columns.iter().position(|c| c.trim() == "size_bytes").unwrap_or(2);
+    let human_column = columns.iter().position(|c| c.trim() == "size_human").unwrap_or_else(|| {
+        columns.push("size_human".to_string());
+        columns.len() - 1
+    });
+    let mut out = format!("{}\n", join_csv_fields(&columns, sep));
+    
+    let mut exported = 0;
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields = split_csv_line(&line, sep);
+        let human = fields
+            .get(size_column)
+            .and_then(|size| size.trim().parse::<u64>().ok())
+            .map(|bytes| convert_bytes(bytes as f64))
+            .unwrap_or_default();
+        if fields.len() <= human_column {
+            fields.resize(human_column + 1, String::new());
+        }
+        fields[human_column] = human;
+        out.push_str(&join_csv_fields(&fields, sep));
+        out.push('\n');
+        exported += 1;
+    }
+    write_csv_file(dst, &out)?;
+    
+    Ok(exported)
+}
+
+// Posición en la fila de cada columna de CSV_HEADER (None si el archivo no la tiene)
+struct ColumnLayout([Option<usize>; 10]);
+
+impl ColumnLayout {
+    fn positional() -> Self {
+        Self(std::array::from_fn(Some))
+    }
+    
+    // Por nombre de columna, en cualquier orden. None si la línea no parece
+    // una cabecera (no tiene columna infohash).
+    fn from_header(header: &str, sep: char) -> Option<Self> {
+        let names: Vec<String> = split_csv_line(header.trim_end(), sep)
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        if !names.iter().any(|name| name == "infohash") {
+            return None;
+        }
+        let mut layout = [None; 10];
+        for (column, expected) in CSV_HEADER.split(';').enumerate() {
+            layout[column] = names.iter().position(|name| name == expected);
+        }
+        Some(Self(layout))
+    }
+}
+
+// Lee todas las filas válidas mapeando las columnas por el nombre de la
+// cabecera, así que sirve para archivos de versiones con otro orden. Sin
+// cabecera se usa el orden de CSV_HEADER y la primera línea también es un dato.
+pub fn read_all_records(csv_path: &str, sep: char) -> std::io::Result<Vec<TorrentCsvRecord>> {
+    let mut lines = open_csv_reader(csv_path)?.lines().peekable();
+    let header_layout = match lines.peek() {
+        Some(Ok(first)) => ColumnLayout::from_header(first, sep),
+        _ => None,
+    };
+    let layout = match header_layout {
+        Some(layout) => {
+            lines.next();
+            layout
+        }
+        None => ColumnLayout::positional(),
+    };
+    
+    let mut records = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(record) = TorrentCsvRecord::from_fields(&split_csv_line(&line, sep), &layout) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+// NDJSON: un TorrentCsvRecord en JSON por línea. Un archivo inexistente se
+// lee como vacío y las líneas que no se pueden decodificar se saltan.
+pub fn read_torrents_ndjson(path: &str) -> std::io::Result<Vec<TorrentCsvRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    
+    let mut records = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(e) => eprintln!("Warning: skipping invalid line {} in {}: {}", i + 1, path, e),
+        }
+    }
+    
+    Ok(records)
+}
+
+// Añade al NDJSON los registros cuyo infohash aún no está, como append_torrents_to_csv
+pub fn write_torrents_ndjson(path: &str, records: Vec<TorrentCsvRecord>) -> std::io::Result<usize> {
+    let existing: HashSet<String> = read_torrents_ndjson(path)?
+        .into_iter()
+        .map(|r| r.infohash)
+        .collect();
+    
+    let new_records: Vec<_> = records.into_iter()
+        .filter(|r| !existing.contains(&r.infohash))
+        .collect();
+    
+    if new_records.is_empty() {
+        return Ok(0);
+    }
+    
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(csv_path)?;
+        .open(path)?;
     
-    // Escribir registros
     for record in &new_records {
-        writeln!(file, "{}", record.to_csv_line())?;
+        let json = serde_json::to_string(record).map_err(std::io::Error::other)?;
+        writeln!(file, "{}", json)?;
     }
     
     Ok(new_records.len())
 }
 
-pub fn create_csv_if_not_exists(csv_path: &str) -> std::io::Result<()> {
+pub fn create_csv_if_not_exists(csv_path: &str, sep: char) -> std::io::Result<()> {
     if !Path::new(csv_path).exists() {
-        let mut file = File::create(csv_path)?;
-        writeln!(file, "infohash;name;size_bytes;created_unix;seeders;leechers;completed;scraped_date")?;
+        write_csv_file(csv_path, &format!("{}\n", csv_header(sep)))?;
     }
     Ok(())
 }
 
+// Infohash en hex minúsculas: 40 caracteres (v1) o 64 (v2)
+pub fn normalize_infohash(infohash: &str) -> Option<String> {
+    let infohash = infohash.trim();
+    let valid = matches!(infohash.len(), 40 | 64)
+        && infohash.chars().all(|c| c.is_ascii_hexdigit());
+    
+    valid.then(|| infohash.to_ascii_lowercase())
+}
+
+// Infohash v1 tal cual (sin normalizar): 40 caracteres hex, en mayúsculas o
+// minúsculas. Es lo único que se puede scrapear por UDP.
+pub fn is_valid_infohash(s: &str) -> bool {
+    s.len() == 40 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfohashVersion {
+    V1,
+    V2,
+    // Magnet con btih y btmh; se usa el hash v1
+    Hybrid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagnetInfohash {
+    pub hash: String,
+    pub version: InfohashVersion,
+}
+
+// Base32 RFC 4648 sin padding
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+    
+    for c in input.trim_end_matches('=').chars() {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u64 - 'A' as u64,
+            c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    
+    Some(bytes)
+}
+
+pub fn extract_magnet_infohash(magnet: &str) -> Option<MagnetInfohash> {
+    // magnet:?xt=urn:btih:INFOHASH y/o xt=urn:btmh:1220HASHV2 (también xt.1=, xt.2=...)
+    let query = magnet.split_once('?').map(|(_, q)| q).unwrap_or(magnet);
+    let mut v1 = None;
+    let mut v2 = None;
+    
+    for param in query.split('&') {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        if key != "xt" && !key.starts_with("xt.") {
+            continue;
+        }
+        
+        if let Some(hash) = value.strip_prefix("urn:btih:") {
+            // Algunos magnets usan base32 (32 caracteres) en lugar de hex
+            let hash = match hash.len() {
+                32 => base32_decode(hash).map(hex::encode).unwrap_or_else(|| hash.to_lowercase()),
+                _ => hash.to_lowercase(),
+            };
+            v1.get_or_insert(hash);
+        } else if let Some(multihash) = value.strip_prefix("urn:btmh:") {
+            // Prefijo multihash 0x12 (sha2-256) 0x20 (32 bytes)
+            let hash = multihash.strip_prefix("1220").unwrap_or(multihash);
+            v2.get_or_insert_with(|| hash.to_lowercase());
+        }
+    }
+    
+    match (v1, v2) {
+        (Some(hash), Some(_)) => Some(MagnetInfohash { hash, version: InfohashVersion::Hybrid }),
+        (Some(hash), None) => Some(MagnetInfohash { hash, version: InfohashVersion::V1 }),
+        (None, Some(hash)) => Some(MagnetInfohash { hash, version: InfohashVersion::V2 }),
+        (None, None) => None,
+    }
+}
+
 pub fn extract_infohash_from_magnet(magnet: &str) -> Option<String> {
-    // Extraer infohash de magnet link: magnet:?xt=urn:btih:INFOHASH
-    magnet.split("xt=urn:btih:")
-        .nth(1)?
+    extract_magnet_infohash(magnet).map(|m| m.hash)
+}
+
+// Nombre legible del parámetro dn=, decodificando tanto "+" como %XX
+pub fn magnet_display_name(magnet: &str) -> Option<String> {
+    let query = magnet.split_once('?').map(|(_, q)| q).unwrap_or(magnet);
+    let value = query
         .split('&')
-        .next()
-        .map(|s| s.to_lowercase())
+        .find_map(|param| param.strip_prefix("dn="))?;
+    
+    // "+" primero, para que un %2B decodificado siga siendo "+"
+    let spaced = value.replace('+', " ");
+    let decoded = urlencoding::decode_binary(spaced.as_bytes());
+    let name = String::from_utf8_lossy(&decoded).trim().to_string();
+    (!name.is_empty()).then_some(name)
 }
 
 #[cfg(test)]
@@ -146,6 +932,9 @@ mod tests {
         assert_eq!(parse_size_to_bytes("1.5 GB"), 1500000000);
         assert_eq!(parse_size_to_bytes("500 MB"), 500000000);
         assert_eq!(parse_size_to_bytes("2.0 KB"), 2000);
+        assert_eq!(parse_size_to_bytes("1,5 GB"), 1500000000);
+        assert_eq!(parse_size_to_bytes("1,024 MB"), 1024000000);
+        assert_eq!(parse_size_to_bytes("1,234.5 MB"), 1234500000);
     }
     
     #[test]
@@ -153,4 +942,427 @@ mod tests {
         let magnet = "magnet:?xt=urn:btih:ABC123&dn=test";
         assert_eq!(extract_infohash_from_magnet(magnet), Some("abc123".to_string()));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_validate_csv_header() {
+        assert!(validate_csv_header(CSV_HEADER, ';').is_ok());
+        assert!(validate_csv_header("infohash;name;size_bytes;created_unix;seeders;leechers;completed;scraped_date", ';').is_ok());
+        assert!(validate_csv_header("name;infohash;size", ';').is_err());
+        assert!(validate_csv_header(&csv_header('\t'), '\t').is_ok());
+        assert!(validate_csv_header(CSV_HEADER, '\t').is_err());
+    }
+    
+    #[test]
+    fn test_normalize_infohash() {
+        assert_eq!(
+            normalize_infohash(" 631A31DD0A46257D5078C0DEE4E66E26F73E42AC "),
+            Some("631a31dd0a46257d5078c0dee4e66e26f73e42ac".to_string())
+        );
+        assert_eq!(normalize_infohash("631a31dd0a46257d5078c0dee4e66e26f73e42a"), None);
+        assert_eq!(normalize_infohash("zz1a31dd0a46257d5078c0dee4e66e26f73e42ac"), None);
+        
+        assert!(is_valid_infohash("631A31DD0A46257D5078C0DEE4E66E26F73E42ac"));
+        assert!(!is_valid_infohash("zz1a31dd0a46257d5078c0dee4e66e26f73e42ac"));
+        assert!(!is_valid_infohash(" 631a31dd0a46257d5078c0dee4e66e26f73e42a"));
+        assert!(!is_valid_infohash(&"a".repeat(64)));
+    }
+    
+    #[test]
+    fn test_extract_infohash_v2_and_hybrid() {
+        let v2 = "magnet:?xt=urn:btmh:1220CAF1E1C30E81CB361B9EE167C4AA64228A7FA4FA9F6105232B28AD099F3A302E&dn=test";
+        let found = extract_magnet_infohash(v2).unwrap();
+        assert_eq!(found.version, InfohashVersion::V2);
+        assert_eq!(found.hash, "caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e");
+        
+        let hybrid = "magnet:?xt=urn:btih:631A31DD0A46257D5078C0DEE4E66E26F73E42AC&xt=urn:btmh:1220D8DD32AC93357C368556AF3AC1D95C9D76BD0DFF6FA9833ECDAC3D53134EFABB&dn=test";
+        let found = extract_magnet_infohash(hybrid).unwrap();
+        assert_eq!(found.version, InfohashVersion::Hybrid);
+        assert_eq!(found.hash, "631a31dd0a46257d5078c0dee4e66e26f73e42ac");
+    }
+    
+    #[test]
+    fn test_extract_infohash_base32() {
+        let magnet = "magnet:?xt=urn:btih:MMNDDXIKIYSX2UDYYDPOJZTOE33T4QVM&dn=test";
+        assert_eq!(
+            extract_infohash_from_magnet(magnet),
+            Some("631a31dd0a46257d5078c0dee4e66e26f73e42ac".to_string())
+        );
+    }
+    
+    #[test]
+    fn test_magnet_display_name() {
+        let magnet = "magnet:?xt=urn:btih:2c6b6858d61da9543d4231a71db4b1c9264b0685&dn=Some+Movie%20%282023%29%2B1080p&tr=udp%3A%2F%2Ftracker";
+        assert_eq!(magnet_display_name(magnet).as_deref(), Some("Some Movie (2023)+1080p"));
+        assert_eq!(magnet_display_name("magnet:?dn=Caf%C3%A9").as_deref(), Some("Café"));
+        assert_eq!(magnet_display_name("magnet:?xt=urn:btih:abc&dn="), None);
+        assert_eq!(magnet_display_name("magnet:?xt=urn:btih:abc"), None);
+    }
+    
+    #[test]
+    fn test_try_from_torrent() {
+        let mut torrent = crate::Torrent {
+            name: "Ubuntu".to_string(),
+            seeders: 10,
+            leechers: 1,
+            size: "1.0 GB".to_string(),
+            date: Some(1_700_000_000),
+            uploader: String::new(),
+            uploader_status: None,
+            infohash: None,
+            imdb_id: None,
+            files_count: None,
+            link: String::new(),
+            source: String::new(),
+        };
+        assert!(TorrentCsvRecord::try_from_torrent(&torrent).is_none());
+        
+        torrent.infohash = Some("2C6B6858D61DA9543D4231A71DB4B1C9264B0685".to_string());
+        let record = TorrentCsvRecord::try_from_torrent(&torrent).unwrap();
+        assert_eq!(record.infohash, "2c6b6858d61da9543d4231a71db4b1c9264b0685");
+        assert_eq!(record.created_unix, 1_700_000_000);
+    }
+    
+    #[test]
+    fn test_ndjson_round_trip() {
+        let path = std::env::temp_dir().join(format!("records_{}.ndjson", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        
+        let record = TorrentCsvRecord {
+            infohash: "2c6b6858d61da9543d4231a71db4b1c9264b0685".to_string(),
+            name: "Name; with \"quotes\"".to_string(),
+            size_bytes: 1024,
+            created_unix: 1_700_000_000,
+            seeders: 10,
+            leechers: 2,
+            completed: 0,
+            scraped_date: 1_700_000_100,
+            files_count: 3,
+            source: String::new(),
+        };
+        assert_eq!(write_torrents_ndjson(path, vec![record.clone()]).unwrap(), 1);
+        // El mismo infohash no se vuelve a añadir
+        assert_eq!(write_torrents_ndjson(path, vec![record]).unwrap(), 0);
+        
+        let content = std::fs::read_to_string(path).unwrap();
+        let records = read_torrents_ndjson(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        
+        assert_eq!(content.lines().count(), 1);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].infohash, "2c6b6858d61da9543d4231a71db4b1c9264b0685");
+        assert_eq!(records[0].name, "Name; with \"quotes\"");
+        assert_eq!(records[0].files_count, 3);
+    }
+    
+    #[test]
+    fn test_csv_line_round_trip_with_quoting() {
+        let record = TorrentCsvRecord {
+            infohash: "2c6b6858d61da9543d4231a71db4b1c9264b0685".to_string(),
+            name: "Movie; \"Director's Cut\"\t2023".to_string(),
+            size_bytes: 1024,
+            created_unix: 1_700_000_000,
+            seeders: 10,
+            leechers: 2,
+            completed: 5,
+            scraped_date: 1_700_000_100,
+            files_count: 3,
+            source: String::new(),
+        };
+        
+        for sep in [';', ',', '\t'] {
+            let line = record.to_csv_line(sep);
+            let parsed = TorrentCsvRecord::from_csv_line(&line, sep).unwrap();
+            assert_eq!(parsed.name, record.name, "separator {:?}", sep);
+            assert_eq!(parsed.files_count, 3);
+        }
+        
+        assert_eq!(split_csv_line("a;\"b;c\";\"d\"\"e\"", ';'), vec!["a", "b;c", "d\"e"]);
+        assert_eq!(split_csv_line("a;;", ';'), vec!["a", "", ""]);
+    }
+    
+    #[tokio::test]
+    async fn test_append_torrents_to_csv_async() {
+        let path = std::env::temp_dir().join(format!("append_async_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        create_csv_if_not_exists(path, ';').unwrap();
+        
+        let record = |hash: &str| TorrentCsvRecord {
+            infohash: hash.to_string(),
+            name: "Name".to_string(),
+            size_bytes: 1,
+            created_unix: 0,
+            seeders: 1,
+            leechers: 0,
+            completed: 0,
+            scraped_date: 0,
+            files_count: 0,
+            source: String::new(),
+        };
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        
+        assert_eq!(append_torrents_to_csv_async(path, vec![record(&a), record(&a)], ';').await.unwrap(), 1);
+        assert_eq!(append_torrents_to_csv_async(path, vec![record(&a), record(&b)], ';').await.unwrap(), 1);
+        
+        // Sin salto de línea final la fila nueva no se pega a la última
+        let content = std::fs::read_to_string(path).unwrap();
+        std::fs::write(path, content.trim_end()).unwrap();
+        let c = "c".repeat(40);
+        assert_eq!(append_torrents_to_csv_async(path, vec![record(&c)], ';').await.unwrap(), 1);
+        
+        let (existing, skipped) = read_existing_infohashes(path, ';').unwrap();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(existing.len(), 3);
+        assert_eq!(skipped, 0);
+    }
+    
+    #[test]
+    fn test_read_all_records_maps_columns_by_header() {
+        let path = std::env::temp_dir().join(format!("reordered_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let a = "A".repeat(40);
+        let b = "b".repeat(40);
+        
+        // Otro orden y sin files_count ni source
+        let content = format!(
+            "name;infohash;seeders;leechers;size_bytes;created_unix;completed;scraped_date\n\"x; y\";{};7;2;1024;10;3;20\nshort;{}\n",
+            a, b
+        );
+        std::fs::write(path, content).unwrap();
+        let records = read_all_records(path, ';').unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].infohash.as_str(), records[0].name.as_str()), (a.to_lowercase().as_str(), "x; y"));
+        assert_eq!((records[0].seeders, records[0].leechers, records[0].size_bytes), (7, 2, 1024));
+        assert_eq!(records[0].source, "");
+        
+        // Sin cabecera: orden de CSV_HEADER desde la primera línea
+        std::fs::write(path, format!("{};Name;1;0;5;0;0;0;2;tpb\n", b)).unwrap();
+        let records = read_all_records(path, ';').unwrap();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].seeders, records[0].files_count, records[0].source.as_str()), (5, 2, "tpb"));
+    }
+    
+    #[test]
+    fn test_upsert_torrents_to_csv() {
+        let path = std::env::temp_dir().join(format!("upsert_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        std::fs::write(path, format!("{}\n{};\"Old; name\";10;5;1;1;7;100;3;1337x\n", CSV_HEADER, a)).unwrap();
+        
+        let record = |hash: &str, seeders: i32| TorrentCsvRecord {
+            infohash: hash.to_string(),
+            name: "New name".to_string(),
+            size_bytes: 1,
+            created_unix: 0,
+            seeders,
+            leechers: 4,
+            completed: 0,
+            scraped_date: 200,
+            files_count: 0,
+            source: "tpb".to_string(),
+        };
+        
+        let counts = upsert_torrents_to_csv(path, vec![record(&a, 50), record(&b, 2)], ';').unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        
+        assert_eq!(counts, (1, 1));
+        let rows: Vec<&str> = content.lines().skip(1).collect();
+        assert_eq!(rows[0], format!("{};\"Old; name\";10;5;50;4;7;200;3;1337x", a));
+        assert_eq!(rows[1], format!("{};New name;1;0;2;4;0;200;0;tpb", b));
+    }
+    
+    #[test]
+    fn test_upsert_duplicates_and_short_rows() {
+        let path = std::env::temp_dir().join(format!("upsert_dup_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        std::fs::write(path, format!("{}
+{};short
+", CSV_HEADER, a)).unwrap();
+        
+        let record = |hash: &str, seeders: i32| TorrentCsvRecord {
+            infohash: hash.to_string(),
+            name: "Name".to_string(),
+            size_bytes: 1,
+            created_unix: 0,
+            seeders,
+            leechers: 0,
+            completed: 0,
+            scraped_date: 0,
+            files_count: 0,
+            source: "tpb".to_string(),
+        };
+        
+        let records = vec![record(&a, 3), record(&b, 1), record(&b, 2)];
+        let counts = upsert_torrents_to_csv(path, records, ';').unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        
+        // La fila corta se conserva y su registro se añade; de los repetidos gana el último
+        assert_eq!(counts, (0, 2));
+        let rows: Vec<&str> = content.lines().skip(1).collect();
+        assert_eq!(rows, [
+            format!("{};short", a),
+            format!("{};Name;1;0;3;0;0;0;0;tpb", a),
+            format!("{};Name;1;0;2;0;0;0;0;tpb", b),
+        ]);
+    }
+    
+    #[test]
+    fn test_append_keeps_existing_header_width() {
+        let path = std::env::temp_dir().join(format!("append_legacy_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let legacy_header = CSV_HEADER.trim_end_matches(";source");
+        std::fs::write(path, format!("{}\n", legacy_header)).unwrap();
+        
+        let record = TorrentCsvRecord {
+            infohash: "a".repeat(40),
+            name: "Name".to_string(),
+            size_bytes: 1,
+            created_unix: 0,
+            seeders: 1,
+            leechers: 0,
+            completed: 0,
+            scraped_date: 0,
+            files_count: 2,
+            source: "tpb".to_string(),
+        };
+        append_torrents_to_csv(path, vec![record.clone()], ';').unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        
+        let row = content.lines().nth(1).unwrap();
+        assert_eq!(split_csv_line(row, ';').len(), 9);
+        assert_eq!(TorrentCsvRecord::from_csv_line(row, ';').unwrap().source, "");
+        assert_eq!(TorrentCsvRecord::from_csv_line(&record.to_csv_line(';'), ';').unwrap().source, "tpb");
+    }
+    
+    #[test]
+    fn test_export_alive() {
+        let dir = std::env::temp_dir();
+        let src = dir.join(format!("export_src_{}.csv", std::process::id()));
+        let dst = dir.join(format!("export_dst_{}.csv", std::process::id()));
+        let (src, dst) = (src.to_str().unwrap(), dst.to_str().unwrap());
+        let content = format!(
+            "{}\n{};Seeded;1;0;25;1;0;0;2\n{};Weak;1;0;3;1;0;0;1\n{};Dead;1;0;0;0;0;0;1\n",
+            CSV_HEADER, "a".repeat(40), "b".repeat(40), "c".repeat(40)
+        );
+        std::fs::write(src, &content).unwrap();
+        
+        let exported = export_alive(src, dst, 10, ';').unwrap();
+        let out = std::fs::read_to_string(dst).unwrap();
+        let untouched = std::fs::read_to_string(src).unwrap();
+        let _ = std::fs::remove_file(src);
+        let _ = std::fs::remove_file(dst);
+        
+        assert_eq!(exported, 1);
+        assert_eq!(out, format!("{}\n{};Seeded;1;0;25;1;0;0;2\n", CSV_HEADER, "a".repeat(40)));
+        assert_eq!(untouched, content);
+    }
+    
+    #[test]
+    fn test_export_with_size_human() {
+        let dir = std::env::temp_dir();
+        let src = dir.join(format!("human_src_{}.csv", std::process::id()));
+        let dst = dir.join(format!("human_dst_{}.csv", std::process::id()));
+        let (src, dst) = (src.to_str().unwrap(), dst.to_str().unwrap());
+        let content = format!(
+            "{}\n{};\"Big;One\";3700000000;0;25;1;0;0;2;tpb\n{};Tiny;512;0;3;1;0;0;1;1337x\n",
+            CSV_HEADER, "a".repeat(40), "b".repeat(40)
+        );
+        std::fs::write(src, &content).unwrap();
+        
+        let exported = export_with_size_human(src, dst, ';').unwrap();
+        let out = std::fs::read_to_string(dst).unwrap();
+        let untouched = std::fs::read_to_string(src).unwrap();
+        let _ = std::fs::remove_file(src);
+        let _ = std::fs::remove_file(dst);
+        
+        assert_eq!(exported, 2);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], format!("{};size_human", CSV_HEADER));
+        assert_eq!(lines[1], format!("{};\"Big;One\";3700000000;0;25;1;0;0;2;tpb;3.7 GB", "a".repeat(40)));
+        assert_eq!(lines[2], format!("{};Tiny;512;0;3;1;0;0;1;1337x;512.0 bytes", "b".repeat(40)));
+        assert_eq!(untouched, content);
+    }
+    
+    #[test]
+    fn test_top_torrents() {
+        let path = std::env::temp_dir().join(format!("top_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let rows: Vec<String> = [("a", 5), ("b", 40), ("c", 12), ("d", 40), ("e", 1)]
+            .iter()
+            .map(|(c, seeders)| format!("{};{};1;0;{};0;0;0;1", c.repeat(40), c, seeders))
+            .collect();
+        std::fs::write(path, format!("{}\n{}\n", CSV_HEADER, rows.join("\n"))).unwrap();
+        
+        let top = top_torrents(path, 3, ';');
+        let _ = std::fs::remove_file(path);
+        
+        let names: Vec<&str> = top.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["b", "d", "c"]);
+    }
+    
+    #[test]
+    fn test_recent_torrents() {
+        let path = std::env::temp_dir().join(format!("recent_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let rows: Vec<String> = [("a", 300), ("b", 100), ("c", 500), ("d", 200)]
+            .iter()
+            .map(|(c, created)| format!("{};\"{};x\";1;{};9;0;0;0;1", c.repeat(40), c, created))
+            .collect();
+        std::fs::write(path, format!("{}\n{}\n", CSV_HEADER, rows.join("\n"))).unwrap();
+        
+        let recent = recent_torrents(path, 2, ';');
+        let _ = std::fs::remove_file(path);
+        
+        let names: Vec<&str> = recent.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["c;x", "a;x"]);
+    }
+    
+    #[test]
+    fn test_total_swarm() {
+        let path = std::env::temp_dir().join(format!("swarm_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let rows = [
+            format!("{};\"a;b\";1;0;10;3;0;0;1", "a".repeat(40)),
+            format!("{};dead;1;0;0;0;0;0;1", "b".repeat(40)),
+            "not;a;row".to_string(),
+            format!("{};c;1;0;5;7;0;0;1", "c".repeat(40)),
+        ];
+        std::fs::write(path, format!("{}\n{}\n", CSV_HEADER, rows.join("\n"))).unwrap();
+        
+        let totals = total_swarm(path, ';');
+        let _ = std::fs::remove_file(path);
+        
+        assert_eq!(totals, SwarmTotals { seeders: 15, leechers: 10 });
+        assert_eq!(totals.to_string(), "Swarm: 25 peers (15 seeders, 10 leechers)");
+    }
+    
+    #[test]
+    fn test_csv_stats() {
+        let path = std::env::temp_dir().join(format!("stats_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let rows = [
+            format!("{};\"Quoted; name\";1000;0;10;2;0;300;1", "a".repeat(40)),
+            format!("{};dead;500;0;0;0;0;100;1", "b".repeat(40)),
+            format!("{};\"Other \"\"quoted\"\"\";250;0;4;0;0;200;1", "c".repeat(40)),
+            "not;a;row".to_string(),
+        ];
+        std::fs::write(path, format!("{}\n{}\n", CSV_HEADER, rows.join("\n"))).unwrap();
+        
+        let stats = csv_stats(path, ';');
+        let _ = std::fs::remove_file(path);
+        
+        assert_eq!((stats.total, stats.alive, stats.dead), (3, 2, 1));
+        assert_eq!(stats.total_size_bytes, 1750);
+        assert_eq!((stats.median_seeders, stats.max_seeders), (4.0, 10));
+        assert_eq!((stats.oldest_scraped, stats.newest_scraped), (Some(100), Some(300)));
+    }
+}
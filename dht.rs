@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use crate::bencode::Bencode;
+
+// Nodos de arranque públicos del DHT de BitTorrent
+pub const DHT_BOOTSTRAP_NODES: [&str; 3] = [
+    "router.bittorrent.com:6881",
+    "dht.transmissionbt.com:6881",
+    "router.utorrent.com:6881",
+];
+
+// Nodos consultados a la vez en cada ronda
+const DHT_ALPHA: usize = 8;
+const COMPACT_NODE_LEN: usize = 26;
+const COMPACT_PEER_LEN: usize = 6;
+
+#[derive(Debug, Clone)]
+pub struct DhtOpts {
+    pub bootstrap: Vec<String>,
+    // Máximo de nodos a los que se pregunta por cada infohash
+    pub max_queries: usize,
+    // Espera por las respuestas de cada ronda
+    pub round_timeout: Duration,
+    // Con estos peers ya se da el torrent por vivo y se deja de buscar
+    pub enough_peers: usize,
+}
+
+impl Default for DhtOpts {
+    fn default() -> Self {
+        Self {
+            bootstrap: DHT_BOOTSTRAP_NODES.iter().map(|n| n.to_string()).collect(),
+            max_queries: 64,
+            round_timeout: Duration::from_secs(1),
+            enough_peers: 50,
+        }
+    }
+}
+
+fn get_peers_query(node_id: &[u8], infohash: &[u8], transaction_id: &[u8]) -> Vec<u8> {
+    Bencode::dict([
+        ("a", Bencode::dict([("id", Bencode::bytes(node_id)), ("info_hash", Bencode::bytes(infohash))])),
+        ("q", Bencode::bytes("get_peers")),
+        ("t", Bencode::bytes(transaction_id)),
+        ("y", Bencode::bytes("q")),
+    ])
+    .encode()
+}
+
+fn xor_distance(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+fn compact_addr(bytes: &[u8]) -> SocketAddrV4 {
+    let ip = Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]);
+    SocketAddrV4::new(ip, u16::from_be_bytes([bytes[4], bytes[5]]))
+}
+
+// Búsqueda iterativa get_peers (BEP-5): se pregunta a los nodos más cercanos
+// al infohash (distancia XOR) que se van conociendo, hasta agotar
+// `max_queries` o reunir `enough_peers`. Devuelve los peers distintos
+// encontrados; el DHT no distingue seeders de leechers.
+pub fn dht_peer_count(infohash: &[u8], opts: &DhtOpts) -> std::io::Result<usize> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let node_id: [u8; 20] = rand::random();
+    let transaction_id: [u8; 2] = rand::random();
+    let query = get_peers_query(&node_id, infohash, &transaction_id);
+
+    // Los nodos de arranque no tienen id conocido: distancia cero para empezar por ellos
+    let mut candidates: Vec<(Vec<u8>, SocketAddr)> = opts
+        .bootstrap
+        .iter()
+        .filter_map(|node| node.to_socket_addrs().ok())
+        .flatten()
+        .filter(SocketAddr::is_ipv4)
+        .map(|addr| (vec![0; 20], addr))
+        .collect();
+    let mut queried = HashSet::new();
+    let mut peers = HashSet::new();
+    let mut buf = [0u8; 2048];
+
+    while queried.len() < opts.max_queries && peers.len() < opts.enough_peers {
+        candidates.sort();
+        let round: Vec<SocketAddr> = candidates
+            .iter()
+            .map(|(_, addr)| *addr)
+            .filter(|addr| !queried.contains(addr))
+            .take(DHT_ALPHA.min(opts.max_queries - queried.len()))
+            .collect();
+        if round.is_empty() {
+            break;
+        }
+        for addr in &round {
+            queried.insert(*addr);
+            let _ = socket.send_to(&query, addr);
+        }
+
+        let deadline = Instant::now() + opts.round_timeout;
+        let mut pending = round.len();
+        while pending > 0 {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            socket.set_read_timeout(Some(left))?;
+            let Ok((n, from)) = socket.recv_from(&mut buf) else {
+                break;
+            };
+            if !round.contains(&from) {
+                continue;
+            }
+            let Some(message) = Bencode::decode(&buf[..n]) else {
+                continue;
+            };
+            if message.get("t").and_then(Bencode::as_bytes) != Some(&transaction_id[..]) {
+                continue;
+            }
+            pending -= 1;
+
+            let Some(response) = message.get("r") else {
+                continue;
+            };
+            for value in response.get("values").and_then(Bencode::as_list).unwrap_or_default() {
+                if let Some(peer) = value.as_bytes().filter(|b| b.len() == COMPACT_PEER_LEN) {
+                    peers.insert(compact_addr(peer));
+                }
+            }
+            if let Some(nodes) = response.get("nodes").and_then(Bencode::as_bytes) {
+                for node in nodes.chunks_exact(COMPACT_NODE_LEN) {
+                    let addr = SocketAddr::V4(compact_addr(&node[20..]));
+                    if !queried.contains(&addr) {
+                        candidates.push((xor_distance(&node[..20], infohash), addr));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(peers.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // Nodo DHT de prueba: contesta a get_peers con `nodes` o con `values`
+    fn spawn_mock_node(reply: impl Fn() -> Bencode + Send + 'static) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+                let query = Bencode::decode(&buf[..n]).unwrap();
+                assert_eq!(query.get("q").and_then(Bencode::as_bytes), Some(&b"get_peers"[..]));
+                let response = Bencode::dict([
+                    ("r", reply()),
+                    ("t", query.get("t").unwrap().clone()),
+                    ("y", Bencode::bytes("r")),
+                ]);
+                socket.send_to(&response.encode(), peer).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_dht_peer_count_follows_nodes() {
+        let with_peers = spawn_mock_node(|| {
+            Bencode::dict([(
+                "values",
+                Bencode::List(vec![Bencode::bytes([10, 0, 0, 1, 0x1a, 0xe1]), Bencode::bytes([10, 0, 0, 2, 0x1a, 0xe1])]),
+            )])
+        });
+        let SocketAddr::V4(with_peers_v4) = with_peers else { unreachable!() };
+        let router = spawn_mock_node(move || {
+            let mut node = vec![7u8; 20];
+            node.extend_from_slice(&with_peers_v4.ip().octets());
+            node.extend_from_slice(&with_peers_v4.port().to_be_bytes());
+            Bencode::dict([("nodes", Bencode::bytes(node))])
+        });
+
+        let opts = DhtOpts {
+            bootstrap: vec![router.to_string()],
+            round_timeout: Duration::from_millis(300),
+            ..DhtOpts::default()
+        };
+
+        assert_eq!(dht_peer_count(&[7u8; 20], &opts).unwrap(), 2);
+    }
+}
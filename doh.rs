@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use futures::future;
+use serde::Deserialize;
+
+// Endpoints JSON de DNS-over-HTTPS. Se usan por IP para no depender del DNS
+// del sistema, que es justo lo que se quiere evitar.
+pub const CLOUDFLARE_DOH_URL: &str = "https://1.1.1.1/dns-query";
+pub const GOOGLE_DOH_URL: &str = "https://8.8.8.8/resolve";
+
+const DOH_TIMEOUT_SECS: u64 = 5;
+const DNS_TYPE_A: u16 = 1;
+
+#[derive(Debug, Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+// DOH=1 o DOH=cloudflare, DOH=google o directamente una URL https://
+pub fn doh_url_from_setting(value: &str) -> Option<String> {
+    match value.trim() {
+        "1" | "cloudflare" => Some(CLOUDFLARE_DOH_URL.to_string()),
+        "google" => Some(GOOGLE_DOH_URL.to_string()),
+        url if url.starts_with("https://") || url.starts_with("http://") => Some(url.to_string()),
+        _ => None,
+    }
+}
+
+// Primer registro A de la respuesta; los CNAME intermedios se ignoran
+async fn resolve_host(client: &reqwest::Client, doh_url: &str, host: &str) -> Option<IpAddr> {
+    let response = client
+        .get(doh_url)
+        .query(&[("name", host), ("type", "A")])
+        .header("accept", "application/dns-json")
+        .send()
+        .await
+        .ok()?;
+    let body: DohResponse = response.json().await.ok()?;
+    if body.status != 0 {
+        return None;
+    }
+    body.answer
+        .iter()
+        .filter(|answer| answer.record_type == DNS_TYPE_A)
+        .find_map(|answer| answer.data.parse().ok())
+}
+
+// Resuelve por DoH los trackers host:port que no son ya una IP. El mapa se
+// calcula una vez por ejecución; los que fallan se omiten y usarán el DNS del
+// sistema.
+pub async fn resolve_trackers_doh(trackers: &[String], doh_url: &str) -> HashMap<String, SocketAddr> {
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(Duration::from_secs(DOH_TIMEOUT_SECS))
+        .build()
+    else {
+        return HashMap::new();
+    };
+
+    let lookups = trackers.iter().filter(|t| t.parse::<SocketAddr>().is_err()).map(|tracker| {
+        let client = &client;
+        async move {
+            let (host, port) = tracker.rsplit_once(':')?;
+            let port: u16 = port.parse().ok()?;
+            match resolve_host(client, doh_url, host).await {
+                Some(ip) => Some((tracker.clone(), SocketAddr::new(ip, port))),
+                None => {
+                    eprintln!("Warning: DoH could not resolve {}, using system DNS", host);
+                    None
+                }
+            }
+        }
+    });
+
+    future::join_all(lookups).await.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_resolve_trackers_doh() {
+        let server = MockServer::start().await;
+        Mock::given(path("/dns-query"))
+            .and(query_param("name", "tracker.example"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"Status":0,"Answer":[{"name":"tracker.example","type":5,"data":"alias.example."},{"name":"alias.example","type":1,"data":"203.0.113.7"}]}"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(path("/dns-query"))
+            .and(query_param("name", "missing.example"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Status":3}"#))
+            .mount(&server)
+            .await;
+
+        let trackers = vec![
+            "tracker.example:1337".to_string(),
+            "missing.example:6969".to_string(),
+            "127.0.0.1:6969".to_string(),
+        ];
+        let resolved = resolve_trackers_doh(&trackers, &format!("{}/dns-query", server.uri())).await;
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved["tracker.example:1337"], "203.0.113.7:1337".parse().unwrap());
+    }
+}
@@ -0,0 +1,37 @@
+mod torrent_search;
+mod csv_io;
+mod csv_writer;
+mod csv_repair;
+mod csv_diff;
+mod rate_limiter;
+mod magnet_cache;
+mod provider;
+mod output;
+mod mirrors;
+mod user_agents;
+mod progress;
+mod udp_tracker;
+mod doh;
+mod bencode;
+mod dht;
+mod blocklist;
+mod torrent_file;
+
+pub use torrent_search::*;
+pub use csv_io::*;
+pub use csv_writer::*;
+pub use csv_repair::*;
+pub use csv_diff::*;
+pub use rate_limiter::*;
+pub use magnet_cache::*;
+pub use provider::*;
+pub use output::*;
+pub use mirrors::*;
+pub use user_agents::*;
+pub use progress::*;
+pub use udp_tracker::*;
+pub use doh::*;
+pub use bencode::*;
+pub use dht::*;
+pub use blocklist::*;
+pub use torrent_file::*;
@@ -0,0 +1,47 @@
+use std::env;
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+use torrent_search::{extract_infohash_from_magnet, is_valid_infohash, normalize_infohash};
+
+// Solo se imprime un infohash v1 normalizado (40 hex en minúsculas); los
+// fragmentos y los hashes v2 se rechazan. Devuelve false si hubo error.
+fn print_infohash(magnet: &str) -> bool {
+    let magnet = magnet.trim();
+    if magnet.is_empty() {
+        return true;
+    }
+    
+    let infohash = extract_infohash_from_magnet(magnet).and_then(|hash| normalize_infohash(&hash));
+    match infohash {
+        Some(infohash) if is_valid_infohash(&infohash) => {
+            println!("{}", infohash);
+            true
+        }
+        _ => {
+            eprintln!("Error: no valid v1 infohash in: {}", magnet);
+            false
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    
+    // Sin argumentos: leer magnets desde stdin, uno por línea
+    let mut ok = true;
+    if args.is_empty() {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            ok &= print_infohash(&line);
+        }
+    } else {
+        for magnet in &args {
+            ok &= print_infohash(magnet);
+        }
+    }
+    
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
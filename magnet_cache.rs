@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::torrent_search::TorrentData;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    data: TorrentData,
+    cached_at: i64,
+}
+
+// Caché en disco (JSON) de páginas de detalle ya resueltas, por URL y con TTL
+pub struct MagnetCache {
+    path: PathBuf,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl MagnetCache {
+    // Un archivo inexistente o corrupto equivale a una caché vacía
+    pub fn load(path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        let path = path.into();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        
+        Self {
+            path,
+            ttl,
+            entries: Mutex::new(entries),
+        }
+    }
+    
+    fn is_fresh(&self, entry: &CacheEntry, now: i64) -> bool {
+        now - entry.cached_at < self.ttl.as_secs() as i64
+    }
+    
+    pub fn get(&self, url: &str) -> Option<TorrentData> {
+        let now = Utc::now().timestamp();
+        let entries = self.entries.lock().unwrap();
+        
+        entries.get(url)
+            .filter(|entry| self.is_fresh(entry, now))
+            .map(|entry| entry.data.clone())
+    }
+    
+    pub fn insert(&self, url: &str, data: &TorrentData) {
+        let entry = CacheEntry {
+            data: data.clone(),
+            cached_at: Utc::now().timestamp(),
+        };
+        self.entries.lock().unwrap().insert(url.to_string(), entry);
+    }
+    
+    // Guarda la caché descartando las entradas caducadas
+    pub fn save(&self) -> std::io::Result<()> {
+        let now = Utc::now().timestamp();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| self.is_fresh(entry, now));
+        
+        let json = serde_json::to_string(&*entries).map_err(std::io::Error::other)?;
+        fs::write(&self.path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_expired_entries_miss_and_are_dropped_on_save() {
+        let path = std::env::temp_dir().join(format!("magnets_{}.json", std::process::id()));
+        let data = TorrentData {
+            magnet: "magnet:?xt=urn:btih:abc".to_string(),
+            files: vec!["a.mkv".to_string()],
+            page_infohash: None,
+        };
+        
+        let fresh = MagnetCache::load(&path, Duration::from_secs(3600));
+        fresh.insert("https://1337x.to/torrent/1/a/", &data);
+        assert_eq!(fresh.get("https://1337x.to/torrent/1/a/").unwrap().magnet, data.magnet);
+        fresh.save().unwrap();
+        
+        // Con TTL cero todo está caducado: get falla y save no lo conserva
+        let expired = MagnetCache::load(&path, Duration::ZERO);
+        assert!(expired.get("https://1337x.to/torrent/1/a/").is_none());
+        expired.save().unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        
+        assert_eq!(saved, "{}");
+    }
+}
@@ -1,65 +1,494 @@
-mod torrent_search;
-mod csv_writer;
-
+use clap::{Parser, Subcommand, ValueEnum};
 use std::env;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use torrent_search::*;
-use csv_writer::*;
+
+// Pausa por defecto entre páginas de detalle de 1337x
+const DEFAULT_DETAIL_DELAY_MS: u64 = 500;
+// Páginas de detalle pedidas a la vez; independiente del límite de sockets UDP
+const DEFAULT_HTTP_CONCURRENCY: usize = 4;
+// Tiempo máximo por página de detalle antes de saltarse el torrent
+const DEFAULT_DETAIL_TIMEOUT_SECS: u64 = 10;
+// Reintentos del listado de 1337x cuando vuelve vacío, y la espera entre ellos
+const DEFAULT_COLLECT_RETRIES: u32 = 2;
+const DEFAULT_COLLECT_RETRY_DELAY_SECS: u64 = 30;
+
+#[derive(Parser)]
+#[command(name = "torrent-search", about = "Search torrent sites and collect the latest uploads")]
+struct Cli {
+    // Sin subcomando se mantiene el comportamiento de siempre (collect)
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Search providers for a query and print the results as a table")]
+    Search {
+        #[arg(required = true, help = "Text to search for")]
+        query: Vec<String>,
+        #[arg(long, value_enum, help = "Only search this provider (default: all)")]
+        provider: Option<ProviderKind>,
+        #[arg(long, help = "Query at most this many providers at once (default: all)")]
+        max_concurrent_providers: Option<usize>,
+        #[arg(long, help = "Hide results without seeders (default: show them)")]
+        seeded_only: bool,
+    },
+    #[command(about = "Fetch the latest 1337x torrents and append them to the CSV")]
+    Collect,
+    #[command(about = "Copy well-seeded rows of a CSV into a new file")]
+    ExportAlive {
+        #[arg(long, default_value_t = 1, help = "Minimum seeders to keep a row")]
+        min_seeders: i32,
+        #[arg(help = "Source CSV (not modified)")]
+        src: String,
+        #[arg(help = "Destination CSV")]
+        dst: String,
+    },
+    #[command(about = "Copy a CSV adding a human-readable size_human column")]
+    SizeHuman {
+        #[arg(help = "Source CSV (not modified)")]
+        src: String,
+        #[arg(help = "Destination CSV")]
+        dst: String,
+    },
+    #[command(about = "Fix rows with a wrong number of fields in CSV files")]
+    Repair {
+        #[arg(required = true, help = "CSV files to repair in place")]
+        files: Vec<String>,
+    },
+    #[command(about = "Show torrents added, removed and with changed seeders between two CSV snapshots")]
+    Diff {
+        #[arg(long, help = "Print the differences as JSON")]
+        json: bool,
+        #[arg(help = "Older snapshot")]
+        old: String,
+        #[arg(help = "Newer snapshot")]
+        new: String,
+    },
+    #[command(about = "Check CSV infohashes against a directory of {infohash}.torrent files")]
+    Verify {
+        #[arg(help = "CSV file to check")]
+        csv: String,
+        #[arg(help = "Directory with the .torrent files")]
+        dir: String,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ProviderKind {
+    #[value(name = "1337x")]
+    X1337,
+    Tpb,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Obtener el nombre del archivo CSV desde variable de entorno
-    let csv_file = env::var("CSV_FILE").unwrap_or_else(|_| {
-        // Buscar el último archivo torrents_part_*.csv
-        find_latest_csv_file().unwrap_or_else(|| "torrents_part_1.csv".to_string())
-    });
+    let cli = Cli::parse();
+    
+    // ALL_PROXY / HTTPS_PROXY / HTTP_PROXY, aplicados por reqwest a todas las peticiones
+    if let Some(var) = proxy_from_env()? {
+        eprintln!("Routing HTTP requests through the proxy in {}", var);
+    }
+    
+    // Selectores de 1337x, opcionalmente sobreescritos desde un JSON
+    let selectors = match env::var("X1337_SELECTORS_FILE") {
+        Ok(path) => X1337Selectors::from_file(&path)?,
+        Err(_) => X1337Selectors::default(),
+    };
+    
+    // Límite de peticiones por segundo y host, compartido por todas las peticiones
+    let fetch_opts = FetchOpts {
+        rate_limiter: env::var("RATE_LIMIT_RPS")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|rps| *rps > 0.0)
+            .map(|rps| Arc::new(RateLimiter::new(rps))),
+        // Caché en disco de magnets ya resueltos
+        magnet_cache: env::var("MAGNET_CACHE_FILE").ok().map(|path| {
+            let ttl_secs = env::var("MAGNET_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(7 * 24 * 3600);
+            Arc::new(MagnetCache::load(path, Duration::from_secs(ttl_secs)))
+        }),
+        // User-Agents (y cabeceras) a rotar, desde USER_AGENTS_FILE o el pool por defecto
+        headers: Some(Arc::new(match env::var("USER_AGENTS_FILE") {
+            Ok(path) => HeaderRotation::from_file(&path)?,
+            Err(_) => HeaderRotation::default(),
+        })),
+        // ACCEPT_LANGUAGE para pedir otro idioma; por defecto inglés
+        accept_language: env::var("ACCEPT_LANGUAGE").ok(),
+    };
+    
+    // Palabras o regex a descartar de los resultados y del CSV
+    let blocklist = match env::var("BLOCKLIST_FILE") {
+        Ok(path) => Blocklist::from_file(&path)?,
+        Err(_) => Blocklist::default(),
+    };
+    
+    match cli.command.unwrap_or(Command::Collect) {
+        Command::Search { query, provider, max_concurrent_providers, seeded_only } => {
+            let query = query.join(" ");
+            search(&query, provider, max_concurrent_providers, !seeded_only, selectors, fetch_opts, &blocklist).await
+        }
+        Command::Collect => collect(&selectors, &fetch_opts, &blocklist).await,
+        Command::ExportAlive { min_seeders, src, dst } => {
+            let exported = export_alive(&src, &dst, min_seeders, csv_separator())?;
+            println!("✅ Exported {} torrents with {}+ seeders to {}", exported, min_seeders, dst);
+            Ok(())
+        }
+        Command::SizeHuman { src, dst } => {
+            let exported = export_with_size_human(&src, &dst, csv_separator())?;
+            println!("✅ Wrote {} torrents with size_human to {}", exported, dst);
+            Ok(())
+        }
+        Command::Repair { files } => {
+            for file in files {
+                let report = repair_csv(&file, csv_separator())?;
+                println!("📦 {}\n{}", file, report);
+            }
+            Ok(())
+        }
+        Command::Diff { json, old, new } => {
+            let diff = diff_csv(&old, &new, csv_separator())?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                println!("{}", diff);
+            }
+            Ok(())
+        }
+        Command::Verify { csv, dir } => {
+            let report = verify_against_torrent_dir(&csv, &dir, csv_separator())?;
+            println!("📦 {}\n{}", csv, report);
+            Ok(())
+        }
+    }
+}
+
+// Mirrors de 1337x: con DISCOVER_MIRRORS=1 se buscan los que responden (guardados
+// en MIRRORS_CACHE_FILE si está definido); si no hay ninguno, los de siempre
+async fn x1337_proxies() -> Vec<String> {
+    let defaults = TorrentProxies::default().x1337;
+    if env::var("DISCOVER_MIRRORS").map(|v| v != "1").unwrap_or(true) {
+        return defaults;
+    }
     
-    println!("Using CSV file: {}", csv_file);
+    let mirrors = match env::var("MIRRORS_CACHE_FILE") {
+        Ok(path) => {
+            let ttl_secs = env::var("MIRRORS_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24 * 3600);
+            load_or_discover_1337x_mirrors(Path::new(&path), Duration::from_secs(ttl_secs)).await
+        }
+        Err(_) => discover_1337x_mirrors().await,
+    };
     
-    // Crear CSV si no existe
-    create_csv_if_not_exists(&csv_file)?;
+    if mirrors.is_empty() {
+        eprintln!("Warning: no reachable 1337x mirror found, using defaults");
+        return defaults;
+    }
+    println!("Using 1337x mirrors: {}", mirrors.join(", "));
+    mirrors
+}
+
+async fn search(
+    query: &str,
+    only: Option<ProviderKind>,
+    max_concurrent: Option<usize>,
+    include_zero_seeders: bool,
+    selectors: X1337Selectors,
+    fetch_opts: FetchOpts,
+    blocklist: &Blocklist,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut providers: Vec<Box<dyn TorrentProvider>> = Vec::new();
+    // Índices extra descritos con selectores en PROVIDERS_FILE; solo sin --provider
+    if let (None, Ok(path)) = (only, env::var("PROVIDERS_FILE")) {
+        for provider in ConfigurableProvider::from_file(&path, &fetch_opts)? {
+            providers.push(Box::new(provider));
+        }
+    }
+    if only.is_none_or(|kind| kind == ProviderKind::X1337) {
+        providers.push(Box::new(X1337Provider {
+            proxies: x1337_proxies().await,
+            selectors,
+            opts: fetch_opts.clone(),
+        }));
+    }
+    if only.is_none_or(|kind| kind == ProviderKind::Tpb) {
+        providers.push(Box::new(TpbProvider {
+            api_base: APIBAY_URL.to_string(),
+            opts: fetch_opts,
+        }));
+    }
+    
+    let results = search_all(&providers, query, max_concurrent).await;
+    // Resumen por proveedor a stderr, para no mezclarlo con la tabla
+    for result in &results {
+        eprintln!("{}", result);
+    }
+    let torrents = retain_seeded(blocklist.filter(merge_results(results)), include_zero_seeders);
+    if torrents.is_empty() {
+        println!("No results for \"{}\"", query);
+        return Ok(());
+    }
+    print_torrents_table(&torrents);
+    Ok(())
+}
+
+async fn collect(
+    selectors: &X1337Selectors,
+    fetch_opts: &FetchOpts,
+    blocklist: &Blocklist,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Directorio de datos donde viven los torrents_part_*.csv
+    let data_dir = env::var("DATA_DIR").unwrap_or_else(|_| ".".to_string());
+    
+    // Separador de campos del CSV (CSV_SEP, por defecto ';')
+    let sep = csv_separator();
+    
+    // COUNT_ONLY=1: solo el listado (cuántos hay y una tabla), sin páginas de
+    // detalle ni escribir nada
+    let count_only = env::var("COUNT_ONLY").is_ok_and(|v| v == "1");
+    
+    // OUTPUT=ndjson escribe un objeto JSON por línea en lugar del CSV
+    let ndjson = env::var("OUTPUT").map(|v| v == "ndjson").unwrap_or(false);
+    
+    let output_file = if ndjson {
+        let ndjson_name = env::var("NDJSON_FILE").unwrap_or_else(|_| "torrents.ndjson".to_string());
+        let ndjson_file = Path::new(&data_dir).join(ndjson_name).to_string_lossy().into_owned();
+        println!("Using NDJSON file: {}", ndjson_file);
+        ndjson_file
+    } else {
+        // Obtener el nombre del archivo CSV desde variable de entorno
+        let csv_name = env::var("CSV_FILE").unwrap_or_else(|_| {
+            // Buscar el último archivo torrents_part_*.csv
+            find_latest_csv_file(&data_dir).unwrap_or_else(|| "torrents_part_1.csv".to_string())
+        });
+        let csv_file = Path::new(&data_dir).join(csv_name).to_string_lossy().into_owned();
+        println!("Using CSV file: {}", csv_file);
+        
+        // Crear CSV si no existe
+        if !count_only {
+            create_csv_if_not_exists(&csv_file, sep)?;
+        }
+        csv_file
+    };
     
     let mut all_records = Vec::new();
     
     println!("Fetching latest torrents...");
     
     // Obtener los últimos torrents de 1337x (sin búsqueda específica)
-    let torrents = get_latest_torrents_1337x().await;
+    // Solo torrents nuevos desde SINCE_UNIX o desde la última ejecución guardada en LAST_RUN_FILE
+    let last_run_file = env::var("LAST_RUN_FILE").ok();
+    let since_unix = env::var("SINCE_UNIX")
+        .ok()
+        .or_else(|| last_run_file.as_ref().and_then(|path| std::fs::read_to_string(path).ok()))
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(0);
+    let keep_undated = env::var("KEEP_UNDATED").map(|v| v != "0").unwrap_or(true);
+    // DETAIL_DELAY_MS=0 quita la pausa (p. ej. con un mirror sin límite de peticiones)
+    let detail_delay = Duration::from_millis(
+        env::var("DETAIL_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_DETAIL_DELAY_MS),
+    );
+    let detail_timeout = Duration::from_secs(
+        env::var("DETAIL_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_DETAIL_TIMEOUT_SECS),
+    );
+    let http_concurrency = env::var("HTTP_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_HTTP_CONCURRENCY);
+    let run_started = chrono::Utc::now().timestamp();
+    
+    // Un listado vacío suele ser un fallo del mirror: se reintenta y, si sigue
+    // vacío, la ejecución falla para que el planificador no la dé por buena
+    let retries = env::var("COLLECT_RETRIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_COLLECT_RETRIES);
+    let retry_delay = Duration::from_secs(
+        env::var("COLLECT_RETRY_DELAY_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_COLLECT_RETRY_DELAY_SECS),
+    );
+    let proxies = x1337_proxies().await;
+    let torrents = fetch_latest_with_retries(&proxies, selectors, fetch_opts, retries, retry_delay).await?;
+    // Sin subidas nuevas desde `since_unix` no es un fallo: solo se reintenta con el listado vacío
+    let torrents = filter_since(torrents, since_unix, keep_undated);
     println!("Found {} latest torrents", torrents.len());
+    let torrents = SizeFilter::from_env().filter(blocklist.filter(retain_valid(torrents)));
     
-    // Para cada torrent, obtener el magnet link y crear registro
-    for torrent in torrents.iter() {
-        match get_1337x_torrent_data(&torrent.link).await {
-            data if !data.magnet.is_empty() => {
-                if let Some(infohash) = extract_infohash_from_magnet(&data.magnet) {
-                    let record = TorrentCsvRecord::from_torrent(torrent, &infohash);
-                    all_records.push(record);
-                    println!("  + Added: {} ({} seeders)", torrent.name, torrent.seeders);
-                }
-            }
-            _ => println!("  - Skipped (no magnet): {}", torrent.name),
+    if count_only {
+        print_torrents_table(&torrents);
+        println!("\n📊 {} torrents in the latest listing", torrents.len());
+        return Ok(());
+    }
+    
+    // Los que ya traen infohash (p. ej. de apibay) no necesitan la página de detalle
+    let (known, pending): (Vec<_>, Vec<_>) = torrents
+        .into_iter()
+        .partition(|t| t.infohash.is_some());
+    for torrent in &known {
+        if let Some(record) = TorrentCsvRecord::try_from_torrent(torrent) {
+            all_records.push(record);
+            println!("  + Added: {} ({} seeders)", torrent.name, torrent.seeders);
+        }
+    }
+    
+    // Para el resto, obtener el magnet link y crear registro
+    let resolved = resolve_magnets(
+        &pending,
+        http_concurrency,
+        detail_delay,
+        detail_timeout,
+        selectors,
+        fetch_opts,
+    ).await;
+    
+    for (torrent, data) in &resolved {
+        if data.magnet.is_empty() {
+            println!("  - Skipped (no magnet): {}", torrent.name);
+            continue;
         }
         
-        // Pequeña pausa entre requests
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // resolve_magnets ya rellena el infohash verificado a partir del magnet
+        match TorrentCsvRecord::try_from_torrent(torrent) {
+            Some(mut record) => {
+                record.files_count = data.files.len() as u32;
+                all_records.push(record);
+                println!("  + Added: {} ({} seeders)", torrent.name, torrent.seeders);
+            }
+            None => println!("  - Skipped (unverified infohash): {}", torrent.name),
+        }
+    }
+    
+    if let Some(cache) = &fetch_opts.magnet_cache {
+        cache.save()?;
     }
     
-    // Guardar todos los registros en el CSV
-    let added = append_torrents_to_csv(&csv_file, all_records)?;
-    println!("\n✅ Added {} new torrents to {}", added, csv_file);
+    // Solo infohashes v1 válidos: el resto no se podría scrapear después
+    let before = all_records.len();
+    all_records.retain(|record| is_valid_infohash(&record.infohash));
+    if all_records.len() < before {
+        eprintln!("Warning: skipped {} torrents with an invalid infohash", before - all_records.len());
+    }
+    
+    // Guardar todos los registros en el CSV (o NDJSON). Con UPSERT=1 también se
+    // refrescan los seeders de los que ya estaban, reescribiendo el archivo.
+    let upsert = env::var("UPSERT").is_ok_and(|v| v == "1");
+    let (added, updated) = if ndjson {
+        (write_torrents_ndjson(&output_file, all_records)?, None)
+    } else if upsert {
+        let (updated, added) = upsert_torrents_to_csv(&output_file, all_records, sep)?;
+        (added, Some(updated))
+    } else {
+        (append_torrents_to_csv_async(&output_file, all_records, sep).await?, None)
+    };
+    println!("\n✅ Added {} new torrents to {}", added, output_file);
+    if let Some(updated) = updated {
+        println!("🔄 Updated {} existing torrents", updated);
+    }
+    
+    if let Some(path) = last_run_file {
+        std::fs::write(path, run_started.to_string())?;
+    }
     
     Ok(())
 }
 
-fn find_latest_csv_file() -> Option<String> {
+// Rango de tamaños admitido en el CSV (MIN_SIZE_BYTES / MAX_SIZE_BYTES). Los
+// de tamaño desconocido (0) se guardan salvo con KEEP_UNKNOWN_SIZE=0.
+struct SizeFilter {
+    min: Option<u64>,
+    max: Option<u64>,
+    keep_unknown: bool,
+}
+
+impl SizeFilter {
+    fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+    
+    // Igual que from_env pero leyendo de `var`, para los tests
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let bytes = |name| var(name).and_then(|v| v.trim().parse().ok());
+        Self {
+            min: bytes("MIN_SIZE_BYTES"),
+            max: bytes("MAX_SIZE_BYTES"),
+            keep_unknown: var("KEEP_UNKNOWN_SIZE").is_none_or(|v| v != "0"),
+        }
+    }
+    
+    fn allows(&self, size_bytes: u64) -> bool {
+        if size_bytes == 0 {
+            return self.keep_unknown;
+        }
+        self.min.is_none_or(|min| size_bytes >= min) && self.max.is_none_or(|max| size_bytes <= max)
+    }
+    
+    // Quita los torrents fuera del rango antes de pedir sus páginas de detalle
+    fn filter(&self, torrents: Vec<Torrent>) -> Vec<Torrent> {
+        torrents
+            .into_iter()
+            .filter(|torrent| {
+                let allowed = self.allows(parse_size_to_bytes(&torrent.size));
+                if !allowed {
+                    println!("  - Skipped (size {}): {}", torrent.size, torrent.name);
+                }
+                allowed
+            })
+            .collect()
+    }
+}
+
+async fn fetch_latest_with_retries(
+    proxies: &[String],
+    selectors: &X1337Selectors,
+    fetch_opts: &FetchOpts,
+    retries: u32,
+    retry_delay: Duration,
+) -> Result<Vec<Torrent>, String> {
+    let mut attempt = 0;
+    loop {
+        let torrents = fetch_latest_listing_1337x(proxies, selectors, fetch_opts).await;
+        if !torrents.is_empty() {
+            return Ok(torrents);
+        }
+        if attempt >= retries {
+            return Err(format!("no torrents fetched after {} attempts", attempt + 1));
+        }
+        attempt += 1;
+        eprintln!("No torrents fetched, retrying in {}s ({}/{})", retry_delay.as_secs(), attempt, retries);
+        tokio::time::sleep(retry_delay).await;
+    }
+}
+
+fn find_latest_csv_file(data_dir: &str) -> Option<String> {
     use std::fs;
     
-    let mut csv_files: Vec<_> = fs::read_dir(".")
+    let mut csv_files: Vec<_> = fs::read_dir(data_dir)
         .ok()?
         .filter_map(|entry| entry.ok())
         .filter(|entry| {
             entry.file_name()
                 .to_str()
-                .map(|name| name.starts_with("torrents_part_") && name.ends_with(".csv"))
+                .map(|name| {
+                    name.starts_with("torrents_part_") && (name.ends_with(".csv") || name.ends_with(".csv.gz"))
+                })
                 .unwrap_or(false)
         })
         .collect();
@@ -71,4 +500,77 @@ fn find_latest_csv_file() -> Option<String> {
     
     csv_files.first()
         .and_then(|entry| entry.file_name().to_str().map(String::from))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_size_filter() {
+        let vars = |pairs: &'static [(&str, &str)]| {
+            move |name: &str| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        
+        let bounded = SizeFilter::from_vars(vars(&[("MIN_SIZE_BYTES", "100"), ("MAX_SIZE_BYTES", " 1000 ")]));
+        assert!(!bounded.allows(99));
+        assert!(bounded.allows(100));
+        assert!(bounded.allows(1000));
+        assert!(!bounded.allows(1001));
+        // Tamaño desconocido: se guarda por defecto aunque haya mínimo
+        assert!(bounded.allows(0));
+        
+        let strict = SizeFilter::from_vars(vars(&[("KEEP_UNKNOWN_SIZE", "0")]));
+        assert!(!strict.allows(0));
+        assert!(strict.allows(1));
+        assert!(SizeFilter::from_vars(vars(&[("KEEP_UNKNOWN_SIZE", "1")])).allows(0));
+    }
+    
+    #[tokio::test]
+    async fn test_collect_no_new_uploads_is_not_retried() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/trending"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(include_str!("fixtures/1337x_trending.html")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let torrents = fetch_latest_with_retries(
+            &[server.uri()],
+            &X1337Selectors::default(),
+            &FetchOpts::default(),
+            2,
+            Duration::ZERO,
+        ).await.unwrap();
+        assert!(!torrents.is_empty());
+        // Todas las filas son anteriores a `since_unix`: lista vacía, sin reintentos ni error
+        assert!(filter_since(torrents, chrono::Utc::now().timestamp(), false).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_collect_empty_listing_fails_after_retries() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/trending"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+            .expect(3)
+            .mount(&server)
+            .await;
+        
+        let result = fetch_latest_with_retries(
+            &[server.uri()],
+            &X1337Selectors::default(),
+            &FetchOpts::default(),
+            2,
+            Duration::ZERO,
+        ).await;
+        assert_eq!(result.unwrap_err(), "no torrents fetched after 3 attempts");
+    }
+}
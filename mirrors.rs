@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use chrono::Utc;
+use futures::future;
+use serde::{Deserialize, Serialize};
+use crate::torrent_search::is_challenge_page;
+use crate::user_agents::DEFAULT_USER_AGENT;
+
+// Dominios conocidos de 1337x; rotan a menudo, por eso se comprueban antes de usarlos
+pub const X1337_MIRROR_CANDIDATES: [&str; 8] = [
+    "https://1337xx.to",
+    "https://1337x.to",
+    "https://1337x.st",
+    "https://1337x.gd",
+    "https://1337x.is",
+    "https://x1337x.ws",
+    "https://x1337x.eu",
+    "https://x1337x.se",
+];
+
+const PROBE_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MirrorCache {
+    discovered_at: i64,
+    mirrors: Vec<String>,
+}
+
+// La portada de un mirror real tiene el formulario de búsqueda y el enlace a
+// trending; descarta dominios aparcados y páginas de challenge
+fn looks_like_1337x(html: &str) -> bool {
+    !is_challenge_page(html) && html.contains("search-index-form") && html.contains("/trending")
+}
+
+async fn probe_mirror(client: &reqwest::Client, mirror: &str) -> bool {
+    let Ok(response) = client.get(format!("{}/", mirror)).send().await else {
+        return false;
+    };
+    if response.status() != reqwest::StatusCode::OK {
+        return false;
+    }
+    response.text().await.map(|html| looks_like_1337x(&html)).unwrap_or(false)
+}
+
+// Prueba todos los candidatos a la vez y devuelve los que responden, en el
+// orden de entrada
+pub async fn probe_1337x_mirrors(candidates: &[String], timeout: Duration) -> Vec<String> {
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(timeout)
+        .gzip(true)
+        .brotli(true)
+        .user_agent(DEFAULT_USER_AGENT)
+        .build()
+    else {
+        return Vec::new();
+    };
+
+    let candidates: Vec<&str> = candidates.iter().map(|m| m.trim_end_matches('/')).collect();
+    let alive = future::join_all(candidates.iter().map(|mirror| probe_mirror(&client, mirror))).await;
+    candidates
+        .into_iter()
+        .zip(alive)
+        .filter(|(_, ok)| *ok)
+        .map(|(mirror, _)| mirror.to_string())
+        .collect()
+}
+
+pub async fn discover_1337x_mirrors() -> Vec<String> {
+    let candidates: Vec<String> = X1337_MIRROR_CANDIDATES.iter().map(|m| m.to_string()).collect();
+    probe_1337x_mirrors(&candidates, Duration::from_secs(PROBE_TIMEOUT_SECS)).await
+}
+
+// Usa la lista guardada en `path` mientras no caduque; si no, vuelve a
+// descubrir y la guarda. Una lista vacía no se guarda, para reintentar la
+// próxima vez.
+pub async fn load_or_discover_1337x_mirrors(path: &Path, ttl: Duration) -> Vec<String> {
+    let now = Utc::now().timestamp();
+    let cached = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<MirrorCache>(&content).ok())
+        .filter(|cache| now - cache.discovered_at < ttl.as_secs() as i64 && !cache.mirrors.is_empty());
+    if let Some(cache) = cached {
+        return cache.mirrors;
+    }
+
+    let mirrors = discover_1337x_mirrors().await;
+    if !mirrors.is_empty() {
+        let cache = MirrorCache { discovered_at: now, mirrors: mirrors.clone() };
+        let saved = serde_json::to_string(&cache)
+            .map_err(std::io::Error::other)
+            .and_then(|json| fs::write(path, json));
+        if let Err(e) = saved {
+            eprintln!("Warning: could not save mirror cache {}: {}", path.display(), e);
+        }
+    }
+    mirrors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const HOMEPAGE: &str = r#"<html><title>1337x</title><form id="search-index-form"></form><a href="/trending">Trending</a></html>"#;
+
+    #[tokio::test]
+    async fn test_probe_1337x_mirrors_keeps_only_real_mirrors() {
+        let good = MockServer::start().await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(HOMEPAGE))
+            .mount(&good)
+            .await;
+        let parked = MockServer::start().await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>This domain is for sale</html>"))
+            .mount(&parked)
+            .await;
+        let broken = MockServer::start().await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(502))
+            .mount(&broken)
+            .await;
+
+        let candidates = vec![parked.uri(), format!("{}/", good.uri()), broken.uri()];
+        let mirrors = probe_1337x_mirrors(&candidates, Duration::from_secs(2)).await;
+
+        assert_eq!(mirrors, vec![good.uri()]);
+    }
+
+    #[tokio::test]
+    async fn test_load_or_discover_uses_fresh_cache() {
+        let cache_path = std::env::temp_dir().join(format!("mirrors_{}.json", std::process::id()));
+        let cache = MirrorCache {
+            discovered_at: Utc::now().timestamp(),
+            mirrors: vec!["https://cached.example".to_string()],
+        };
+        fs::write(&cache_path, serde_json::to_string(&cache).unwrap()).unwrap();
+
+        let mirrors = load_or_discover_1337x_mirrors(&cache_path, Duration::from_secs(3600)).await;
+
+        let _ = fs::remove_file(&cache_path);
+        assert_eq!(mirrors, vec!["https://cached.example".to_string()]);
+    }
+}
@@ -0,0 +1,107 @@
+use comfy_table::{presets, Cell, CellAlignment, ContentArrangement, Table};
+use std::io::IsTerminal;
+
+use super::Torrent;
+
+// Ancho aproximado que ocupan las columnas fijas (seeders, leechers, tamaño,
+// uploader) más los bordes; el resto queda para el nombre
+const FIXED_COLUMNS_WIDTH: usize = 60;
+const MIN_NAME_WIDTH: usize = 20;
+
+pub fn print_torrents_table(torrents: &[Torrent]) {
+    // Fuera de una terminal no hay ancho que respetar: tabla sin bordes y
+    // nombres completos para que se pueda filtrar con grep/awk
+    let width = if std::io::stdout().is_terminal() {
+        Table::new().width().or(Some(120))
+    } else {
+        None
+    };
+    println!("{}", torrents_table(torrents, width));
+}
+
+pub fn torrents_table(torrents: &[Torrent], width: Option<u16>) -> String {
+    let mut table = Table::new();
+    match width {
+        Some(width) => {
+            table
+                .load_preset(presets::UTF8_FULL_CONDENSED)
+                .set_content_arrangement(ContentArrangement::Dynamic)
+                .set_width(width);
+        }
+        None => {
+            table.load_preset(presets::NOTHING).force_no_tty();
+        }
+    }
+    let name_width = width.map(|w| (w as usize).saturating_sub(FIXED_COLUMNS_WIDTH).max(MIN_NAME_WIDTH));
+
+    table.set_header(vec!["NAME", "SEEDS", "LEECH", "SIZE", "UPLOADER"]);
+    for t in torrents {
+        let name = match name_width {
+            Some(max) => ellipsize(&t.name, max),
+            None => t.name.clone(),
+        };
+        let uploader = match &t.uploader_status {
+            Some(status) => format!("{} ({})", t.uploader, status),
+            None => t.uploader.clone(),
+        };
+        table.add_row(vec![
+            Cell::new(name),
+            Cell::new(t.seeders).set_alignment(CellAlignment::Right),
+            Cell::new(t.leechers).set_alignment(CellAlignment::Right),
+            Cell::new(&t.size).set_alignment(CellAlignment::Right),
+            Cell::new(uploader),
+        ]);
+    }
+
+    table.to_string()
+}
+
+// Recorta por caracteres (no bytes) para no partir UTF-8
+fn ellipsize(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    text.chars().take(max.saturating_sub(1)).chain(std::iter::once('…')).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent(name: &str) -> Torrent {
+        Torrent {
+            name: name.to_string(),
+            seeders: 1234,
+            leechers: 56,
+            size: "1.5 GB".to_string(),
+            date: None,
+            uploader: "someuser".to_string(),
+            uploader_status: Some("VIP".to_string()),
+            infohash: None,
+            imdb_id: None,
+            files_count: None,
+            link: String::new(),
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_torrents_table_ellipsizes_long_names() {
+        let long_name = "Some.Very.Long.Release.Name.".repeat(10);
+        let table = torrents_table(&[torrent(&long_name)], Some(100));
+
+        assert!(table.contains('…'));
+        assert!(!table.contains(&long_name));
+        assert!(table.contains("someuser (VIP)"));
+        assert!(table.lines().all(|line| line.chars().count() <= 100));
+    }
+
+    #[test]
+    fn test_torrents_table_without_tty_keeps_full_names() {
+        let long_name = "Some.Very.Long.Release.Name.".repeat(10);
+        let table = torrents_table(&[torrent(&long_name)], None);
+
+        assert!(table.contains(&long_name));
+        assert!(!table.contains('│'));
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::Write;
+use std::sync::Arc;
+
+// Estado del scrape de un archivo, enviado tras cada tanda de batches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressUpdate {
+    pub processed: usize,
+    pub alive: usize,
+    pub dead: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl ProgressUpdate {
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+        self.processed as f64 / self.total as f64 * 100.0
+    }
+}
+
+// Se llama desde varios hilos, por eso Send + Sync
+pub type ProgressCallback = Arc<dyn Fn(ProgressUpdate) + Send + Sync>;
+
+// Impresora por defecto: una sola línea que se reescribe con '\r'
+pub fn print_progress(update: ProgressUpdate) {
+    print!(
+        "\r🚀 Progress: {:.2}% ({}/{}) | Alive: {} | Dead: {} | Failed: {}   ",
+        update.percent().round(),
+        update.processed,
+        update.total,
+        update.alive,
+        update.dead,
+        update.failed
+    );
+    let _ = std::io::stdout().flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_percent() {
+        let update = ProgressUpdate { processed: 25, alive: 20, dead: 3, failed: 2, total: 200 };
+        assert_eq!(update.percent(), 12.5);
+        assert_eq!(ProgressUpdate::default().percent(), 100.0);
+    }
+}
@@ -0,0 +1,383 @@
+use futures::future::{self, BoxFuture};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use super::{
+    get_with_retry, normalize_size, parse_date, search_1337x, search_tpb_api, to_int, FetchOpts, SearchError, Torrent,
+    X1337Selectors,
+};
+
+// Fuente de torrents que sabe buscar por texto. Devuelve un BoxFuture para
+// poder guardar proveedores distintos como Box<dyn TorrentProvider>.
+// Los proveedores nunca filtran: devuelven todo lo que da el índice, también
+// los torrents sin seeders, y la política (retain_seeded...) es del llamador.
+pub trait TorrentProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<Torrent>, SearchError>>;
+}
+
+pub struct X1337Provider {
+    pub proxies: Vec<String>,
+    pub selectors: X1337Selectors,
+    pub opts: FetchOpts,
+}
+
+impl TorrentProvider for X1337Provider {
+    fn name(&self) -> &str {
+        "1337x"
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<Torrent>, SearchError>> {
+        Box::pin(search_1337x(&self.proxies, query, &self.selectors, &self.opts))
+    }
+}
+
+pub struct TpbProvider {
+    pub api_base: String,
+    pub opts: FetchOpts,
+}
+
+impl TorrentProvider for TpbProvider {
+    fn name(&self) -> &str {
+        "tpb"
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<Torrent>, SearchError>> {
+        Box::pin(search_tpb_api(&self.api_base, query, &self.opts))
+    }
+}
+
+// Selectores CSS de un índice genérico. `date` y `link` son opcionales: sin
+// `link` se usa el href del elemento del nombre.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderSelectors {
+    pub row: String,
+    pub name: String,
+    pub seeders: String,
+    pub leechers: String,
+    pub size: String,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+// Un índice de torrents descrito solo con configuración (PROVIDERS_FILE)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub name: String,
+    pub base_url: String,
+    // Ruta de búsqueda con {query}, que se sustituye ya codificado para URL
+    pub search_path: String,
+    pub selectors: ProviderSelectors,
+    // Formato chrono de la fecha (p. ej. "%Y-%m-%d"); sin él no se lee la fecha
+    #[serde(default)]
+    pub date_format: Option<String>,
+}
+
+// Selectores ya compilados, para no parsearlos en cada búsqueda
+struct CompiledSelectors {
+    row: Selector,
+    name: Selector,
+    seeders: Selector,
+    leechers: Selector,
+    size: Selector,
+    date: Option<Selector>,
+    link: Option<Selector>,
+}
+
+pub struct ConfigurableProvider {
+    config: ProviderConfig,
+    selectors: CompiledSelectors,
+    opts: FetchOpts,
+}
+
+impl ConfigurableProvider {
+    // Falla si algún selector no es CSS válido, para enterarse al arrancar
+    pub fn new(config: ProviderConfig, opts: FetchOpts) -> Result<Self, Box<dyn std::error::Error>> {
+        let compile = |css: &str| {
+            Selector::parse(css).map_err(|e| format!("{}: invalid selector '{}': {:?}", config.name, css, e))
+        };
+        let selectors = CompiledSelectors {
+            row: compile(&config.selectors.row)?,
+            name: compile(&config.selectors.name)?,
+            seeders: compile(&config.selectors.seeders)?,
+            leechers: compile(&config.selectors.leechers)?,
+            size: compile(&config.selectors.size)?,
+            date: config.selectors.date.as_deref().map(compile).transpose()?,
+            link: config.selectors.link.as_deref().map(compile).transpose()?,
+        };
+        Ok(Self { config, selectors, opts })
+    }
+
+    // PROVIDERS_FILE: lista JSON de ProviderConfig
+    pub fn from_file(path: &str, opts: &FetchOpts) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        let configs: Vec<ProviderConfig> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        configs.into_iter().map(|config| Self::new(config, opts.clone())).collect()
+    }
+
+    pub fn parse_listing(&self, html: &str) -> Vec<Torrent> {
+        let document = Html::parse_document(html);
+        let base = reqwest::Url::parse(&self.config.base_url).ok();
+        let text = |el: scraper::ElementRef| el.text().collect::<String>().trim().to_string();
+        let s = &self.selectors;
+
+        document
+            .select(&s.row)
+            .filter_map(|row| {
+                let name = row.select(&s.name).next()?;
+                let link = match &s.link {
+                    Some(link) => row.select(link).next()?,
+                    None => name,
+                };
+                let href = link.value().attr("href")?;
+                let date = s.date.as_ref().and_then(|date| row.select(date).next())
+                    .zip(self.config.date_format.as_deref())
+                    .and_then(|(el, format)| parse_date(&text(el), format));
+                Some(Torrent {
+                    name: text(name),
+                    seeders: row.select(&s.seeders).next().and_then(|el| to_int(&text(el)).ok()).unwrap_or(0),
+                    leechers: row.select(&s.leechers).next().and_then(|el| to_int(&text(el)).ok()).unwrap_or(0),
+                    size: row.select(&s.size).next().and_then(|el| normalize_size(&text(el))).unwrap_or_default(),
+                    date,
+                    uploader: String::new(),
+                    uploader_status: None,
+                    infohash: None,
+                    imdb_id: None,
+                    files_count: None,
+                    link: base
+                        .as_ref()
+                        .and_then(|base| base.join(href).ok())
+                        .map_or_else(|| href.to_string(), String::from),
+                    source: self.config.name.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl TorrentProvider for ConfigurableProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<Torrent>, SearchError>> {
+        Box::pin(async move {
+            let path = self.config.search_path.replace("{query}", &urlencoding::encode(query));
+            let url = format!("{}{}", self.config.base_url.trim_end_matches('/'), path);
+            let html = get_with_retry(&url, &self.opts).await?;
+            Ok(self.parse_listing(&html))
+        })
+    }
+}
+
+// Lo que devolvió un proveedor en search_all y cuánto tardó. Si falla,
+// `torrents` queda vacío y el motivo va en `error`.
+#[derive(Debug)]
+pub struct SearchResult {
+    pub torrents: Vec<Torrent>,
+    pub provider: String,
+    pub elapsed: Duration,
+    pub error: Option<SearchError>,
+}
+
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.elapsed.as_secs_f64();
+        match &self.error {
+            Some(e) => write!(f, "{}: failed after {:.2}s: {}", self.provider, secs, e),
+            None => write!(f, "{}: {} results in {:.2}s", self.provider, self.torrents.len(), secs),
+        }
+    }
+}
+
+// Busca en todos los proveedores, un SearchResult por proveedor en el mismo
+// orden. Con `max_concurrent_providers` se limita cuántos consultan a la vez
+// (None = todos); el tiempo se mide desde que el proveedor obtiene su turno.
+// Un proveedor que falla no invalida los demás.
+pub async fn search_all(
+    providers: &[Box<dyn TorrentProvider>],
+    query: &str,
+    max_concurrent_providers: Option<usize>,
+) -> Vec<SearchResult> {
+    let permits = Semaphore::new(max_concurrent_providers.unwrap_or(providers.len()).max(1));
+    future::join_all(providers.iter().map(|p| async {
+        let _permit = permits.acquire().await.expect("semaphore is never closed");
+        let started = Instant::now();
+        let result = p.search(query).await;
+        let (torrents, error) = match result {
+            Ok(torrents) => (torrents, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        SearchResult {
+            torrents,
+            provider: p.name().to_string(),
+            elapsed: started.elapsed(),
+            error,
+        }
+    }))
+    .await
+}
+
+// Junta los torrents de todos los proveedores, de más a menos seeders
+pub fn merge_results(results: Vec<SearchResult>) -> Vec<Torrent> {
+    let mut torrents: Vec<Torrent> = results.into_iter().flat_map(|r| r.torrents).collect();
+    torrents.sort_by_key(|t| std::cmp::Reverse(t.seeders));
+    torrents
+}
+
+// Quita los torrents sin seeders salvo que se pidan expresamente
+pub fn retain_seeded(mut torrents: Vec<Torrent>, include_zero: bool) -> Vec<Torrent> {
+    if !include_zero {
+        torrents.retain(|t| t.seeders > 0);
+    }
+    torrents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Búsquedas en curso y el máximo simultáneo visto
+    #[derive(Default)]
+    struct InFlight {
+        current: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    struct FakeProvider {
+        seeders: Vec<i32>,
+        fail: bool,
+        in_flight: Option<Arc<InFlight>>,
+    }
+
+    impl TorrentProvider for FakeProvider {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<Torrent>, SearchError>> {
+            Box::pin(async move {
+                if let Some(in_flight) = &self.in_flight {
+                    let current = in_flight.current.fetch_add(1, Ordering::SeqCst) + 1;
+                    in_flight.peak.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    in_flight.current.fetch_sub(1, Ordering::SeqCst);
+                }
+                if self.fail {
+                    return Err(SearchError::InvalidResponse("down".to_string()));
+                }
+                Ok(self
+                    .seeders
+                    .iter()
+                    .map(|&seeders| Torrent {
+                        name: format!("{} {}", query, seeders),
+                        seeders,
+                        leechers: 0,
+                        size: String::new(),
+                        date: None,
+                        uploader: String::new(),
+                        uploader_status: None,
+                        infohash: None,
+                        imdb_id: None,
+                        files_count: None,
+                        link: String::new(),
+                        source: String::new(),
+                    })
+                    .collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retain_seeded() {
+        let provider = FakeProvider { seeders: vec![0, 3], fail: false, in_flight: None };
+        let names = |torrents: Vec<Torrent>| torrents.into_iter().map(|t| t.name).collect::<Vec<_>>();
+
+        let raw = provider.search("q").await.unwrap();
+        assert_eq!(names(retain_seeded(raw.clone(), true)), ["q 0", "q 3"]);
+        assert_eq!(names(retain_seeded(raw, false)), ["q 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_all_merges_and_skips_failures() {
+        let providers: Vec<Box<dyn TorrentProvider>> = vec![
+            Box::new(FakeProvider { seeders: vec![5, 50], fail: false, in_flight: None }),
+            Box::new(FakeProvider { seeders: vec![], fail: true, in_flight: None }),
+            Box::new(FakeProvider { seeders: vec![20], fail: false, in_flight: None }),
+        ];
+
+        let results = search_all(&providers, "ubuntu", None).await;
+
+        assert_eq!(results.len(), 3);
+        assert!(results[1].error.is_some());
+        assert!(results[1].to_string().starts_with("fake: failed after"));
+        assert_eq!(results[2].torrents.len(), 1);
+
+        let torrents = merge_results(results);
+        let seeders: Vec<i32> = torrents.iter().map(|t| t.seeders).collect();
+        assert_eq!(seeders, vec![50, 20, 5]);
+        assert_eq!(torrents[0].name, "ubuntu 50");
+    }
+
+    #[tokio::test]
+    async fn test_configurable_provider() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/find/ubuntu%20iso"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<table><tr class="hit"><td class="t"><a href="/t/1">Ubuntu 24.04</a></td><td class="s">1,204</td>
+                <td class="l">33</td><td class="z">5.7 GB</td><td class="d">2024-04-25</td></tr>
+                <tr class="hit"><td class="t">no link</td></tr></table>"#,
+            ))
+            .mount(&server)
+            .await;
+        let config: ProviderConfig = serde_json::from_str(&format!(
+            r#"{{"name":"example","base_url":"{}","search_path":"/find/{{query}}","date_format":"%Y-%m-%d",
+                "selectors":{{"row":"tr.hit","name":"td.t a","seeders":"td.s","leechers":"td.l",
+                "size":"td.z","date":"td.d"}}}}"#,
+            server.uri()
+        ))
+        .unwrap();
+        let provider = ConfigurableProvider::new(config, FetchOpts::default()).unwrap();
+
+        let torrents = provider.search("ubuntu iso").await.unwrap();
+
+        assert_eq!(torrents.len(), 1);
+        let t = &torrents[0];
+        assert_eq!((t.name.as_str(), t.seeders, t.leechers, t.size.as_str()), ("Ubuntu 24.04", 1204, 33, "5.7 GB"));
+        assert_eq!(t.date, Some(1714003200));
+        assert_eq!(t.link, format!("{}/t/1", server.uri()));
+        assert_eq!(t.source, "example");
+    }
+
+    #[tokio::test]
+    async fn test_search_all_serialized() {
+        let in_flight = Arc::new(InFlight::default());
+        let providers: Vec<Box<dyn TorrentProvider>> = (1..=3)
+            .map(|seeders| {
+                Box::new(FakeProvider { seeders: vec![seeders], fail: false, in_flight: Some(Arc::clone(&in_flight)) })
+                    as Box<dyn TorrentProvider>
+            })
+            .collect();
+
+        let results = search_all(&providers, "ubuntu", Some(1)).await;
+
+        assert!(results.iter().all(|r| r.error.is_none()));
+        assert_eq!(merge_results(results).len(), 3);
+        assert_eq!(in_flight.peak.load(Ordering::SeqCst), 1);
+
+        // Sin límite los tres proveedores se consultan a la vez
+        in_flight.peak.store(0, Ordering::SeqCst);
+        search_all(&providers, "ubuntu", None).await;
+        assert_eq!(in_flight.peak.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+// Token bucket por host: cada host recibe `requests_per_second` tokens por
+// segundo, con una ráfaga máxima de un segundo de tokens.
+pub struct RateLimiter {
+    requests_per_second: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> Self {
+        Self {
+            requests_per_second: requests_per_second.max(f64::MIN_POSITIVE),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    fn try_acquire(&self, host: &str) -> Option<Duration> {
+        let capacity = self.requests_per_second.max(1.0);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(host.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(capacity);
+        bucket.last_refill = now;
+        
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Some(Duration::from_secs_f64(missing / self.requests_per_second))
+        }
+    }
+    
+    // Espera hasta que haya un token disponible para `host`
+    pub async fn acquire(&self, host: &str) {
+        while let Some(wait) = self.try_acquire(host) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_buckets_are_per_host() {
+        let limiter = RateLimiter::new(1.0);
+        assert!(limiter.try_acquire("a.example").is_none());
+        assert!(limiter.try_acquire("a.example").is_some());
+        assert!(limiter.try_acquire("b.example").is_none());
+    }
+}
@@ -0,0 +1,167 @@
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use torrent_search::{
+    build_http_client, get, process_batch, read_csv_to_string, write_csv_file, AdaptiveTimeouts, NetOpts, ScrapeOpts,
+    ScrapeRecord, SocketSemaphore, CSV_HEADER,
+};
+
+// Benchmarks del scrape: `cargo bench --bench scrape_bench`. Criterion muestra
+// el throughput en elementos/s, es decir, hashes por segundo.
+
+const MOCK_CONNECTION_ID: u64 = 0x1122334455667788;
+
+// Tracker UDP local que contesta connect y scrape (BEP-15); los contadores se
+// sacan del propio hash para no tener que guardar estado
+fn spawn_mock_tracker() -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 2048];
+        while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+            if n < 16 {
+                continue;
+            }
+            let action = u32::from_be_bytes(buf[8..12].try_into().unwrap());
+            let mut reply = Vec::new();
+            reply.extend_from_slice(&action.to_be_bytes());
+            reply.extend_from_slice(&buf[12..16]);
+            match action {
+                0 => reply.extend_from_slice(&MOCK_CONNECTION_ID.to_be_bytes()),
+                2 => {
+                    for hash in buf[16..n].chunks(20) {
+                        reply.extend_from_slice(&u32::from(hash[0]).to_be_bytes());
+                        reply.extend_from_slice(&u32::from(hash[1]).to_be_bytes());
+                        reply.extend_from_slice(&u32::from(hash[2]).to_be_bytes());
+                    }
+                }
+                _ => continue,
+            }
+            let _ = socket.send_to(&reply, peer);
+        }
+    });
+
+    addr
+}
+
+// Filas CSV sintéticas con infohashes pseudoaleatorios pero reproducibles
+fn synthetic_lines(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| {
+            let hash = format!("{:08x}{:032x}", i, (i as u128).wrapping_mul(0x9e3779b97f4a7c15));
+            format!("{};Synthetic torrent {};1073741824;1700000000;10;2;5;1700000100;3", hash, i)
+        })
+        .collect()
+}
+
+fn bench_process_batch(c: &mut Criterion) {
+    let trackers: Vec<String> = (0..3).map(|_| spawn_mock_tracker()).collect();
+    let opts = ScrapeOpts {
+        trackers: Arc::new(trackers),
+        socket_limit: Arc::new(SocketSemaphore::new(64)),
+        net: NetOpts {
+            port_range: None,
+            timeouts: Arc::new(AdaptiveTimeouts::new(
+                Duration::from_millis(500),
+                Duration::from_millis(100),
+                Duration::from_millis(1000),
+            )),
+            chunk_sizes: Arc::default(),
+            resolved: Arc::default(),
+            connection_ttl: None,
+            breakers: Arc::default(),
+            transaction_ids: None,
+            announce_first: Arc::default(),
+            cancel: Arc::default(),
+        },
+        csv_sep: ';',
+        update_completed: true,
+        dht: None,
+        on_progress: Arc::new(|_| {}),
+    };
+
+    let mut group = c.benchmark_group("process_batch");
+    for batch_size in [50, 74, 200, 500] {
+        let lines = synthetic_lines(batch_size);
+        let hashes: Vec<String> = lines.iter().map(|line| line[..40].to_string()).collect();
+
+        group.throughput(Throughput::Elements(batch_size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(batch_size), &batch_size, |b, _| {
+            b.iter(|| process_batch((0..batch_size).collect(), hashes.clone(), &lines, &opts));
+        });
+    }
+    group.finish();
+}
+
+fn bench_csv(c: &mut Criterion) {
+    const ROWS: usize = 100_000;
+    let lines = synthetic_lines(ROWS);
+    let content = format!("{}\n{}\n", CSV_HEADER, lines.join("\n"));
+
+    let mut group = c.benchmark_group("csv");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.sample_size(20);
+
+    group.bench_function("parse", |b| {
+        b.iter(|| lines.iter().filter_map(|line| ScrapeRecord::from_line(line, ';')).count());
+    });
+
+    let records: Vec<ScrapeRecord> = lines.iter().filter_map(|line| ScrapeRecord::from_line(line, ';')).collect();
+    for ext in ["csv", "csv.gz"] {
+        let path = std::env::temp_dir().join(format!("scrape_bench_{}.{}", std::process::id(), ext));
+        write_csv_file(&path, &content).unwrap();
+
+        group.bench_function(format!("read_{}", ext), |b| {
+            b.iter(|| read_csv_to_string(&path).unwrap().lines().count());
+        });
+        group.bench_function(format!("write_{}", ext), |b| {
+            b.iter(|| {
+                let mut out = vec![CSV_HEADER.to_string()];
+                out.extend(records.iter().map(|record| record.to_line(';')));
+                write_csv_file(&path, &(out.join("\n") + "\n")).unwrap();
+            });
+        });
+
+        let _ = std::fs::remove_file(&path);
+    }
+    group.finish();
+}
+
+// Coste del handshake: un cliente nuevo por petición abre siempre una conexión,
+// el compartido reutiliza la del pool. El servidor de prueba es HTTP plano, así
+// que solo se mide la reutilización de la conexión TCP; con los mirrors reales
+// (HTTPS) el cliente compartido se ahorra además el handshake TLS.
+fn bench_http_client(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(async {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>ok</html>"))
+            .mount(&server)
+            .await;
+        server
+    });
+    let url = format!("{}/search", server.uri());
+
+    let mut group = c.benchmark_group("http_client");
+    group.bench_function("fresh_client", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let client = build_http_client().unwrap();
+                client.get(&url).send().await.unwrap().text().await.unwrap()
+            })
+        });
+    });
+    group.bench_function("shared_client", |b| {
+        b.iter(|| runtime.block_on(get(&url)).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_process_batch, bench_csv, bench_http_client);
+criterion_main!(benches);
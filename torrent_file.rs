@@ -0,0 +1,127 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::bencode::raw_dict_value;
+use crate::csv_writer::read_all_records;
+
+// Infohash v1 de un .torrent: SHA-1 de los bytes del diccionario `info` tal
+// como están en el archivo. Los clientes hashean esos bytes aunque las claves
+// no estén ordenadas, así que no se vuelve a codificar.
+pub fn torrent_file_infohash(data: &[u8]) -> Option<String> {
+    let info = raw_dict_value(data, "info")?;
+    if *info.first()? != b'd' {
+        return None;
+    }
+    Some(sha1_smol::Sha1::from(info).digest().to_string())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    // Filas con un {infohash}.torrent en el directorio
+    pub checked: usize,
+    pub matched: usize,
+    // Filas sin .torrent con el que comparar
+    pub missing: usize,
+    // (infohash del CSV, infohash calculado del .torrent)
+    pub mismatched: Vec<(String, String)>,
+    // Infohashes cuyo .torrent no se pudo leer o decodificar
+    pub unreadable: Vec<String>,
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Checked:        {}", self.checked)?;
+        writeln!(f, "Matched:        {}", self.matched)?;
+        writeln!(f, "No .torrent:    {}", self.missing)?;
+        writeln!(f, "Unreadable:     {}", self.unreadable.len())?;
+        write!(f, "Mismatched:     {}", self.mismatched.len())?;
+        for (expected, actual) in &self.mismatched {
+            write!(f, "\n  {} -> {}", expected, actual)?;
+        }
+        Ok(())
+    }
+}
+
+// Compara cada infohash del CSV con el que se calcula de `{infohash}.torrent`
+// en `dir`, para detectar entradas corruptas o mal etiquetadas
+pub fn verify_against_torrent_dir(csv_path: &str, dir: &str, sep: char) -> std::io::Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+
+    for record in read_all_records(csv_path, sep)? {
+        let path = Path::new(dir).join(format!("{}.torrent", record.infohash));
+        let path = if path.exists() {
+            path
+        } else {
+            Path::new(dir).join(format!("{}.torrent", record.infohash.to_uppercase()))
+        };
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                report.missing += 1;
+                continue;
+            }
+            Err(_) => {
+                report.checked += 1;
+                report.unreadable.push(record.infohash);
+                continue;
+            }
+        };
+
+        report.checked += 1;
+        match torrent_file_infohash(&data) {
+            Some(actual) if actual == record.infohash => report.matched += 1,
+            Some(actual) => report.mismatched.push((record.infohash, actual)),
+            None => report.unreadable.push(record.infohash),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::Bencode;
+    use crate::csv_writer::CSV_HEADER;
+
+    #[test]
+    fn test_verify_against_torrent_dir() {
+        let dir = std::env::temp_dir().join(format!("torrents_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let torrent = Bencode::dict([
+            ("announce", Bencode::bytes("udp://tracker.example:1337")),
+            ("info", Bencode::dict([("length", Bencode::Int(5)), ("name", Bencode::bytes("a.txt"))])),
+        ])
+        .encode();
+        let good = torrent_file_infohash(&torrent).unwrap();
+        let wrong = "b".repeat(40);
+        fs::write(dir.join(format!("{}.torrent", good)), &torrent).unwrap();
+        fs::write(dir.join(format!("{}.torrent", wrong)), &torrent).unwrap();
+        fs::write(dir.join(format!("{}.torrent", "c".repeat(40))), b"not bencode").unwrap();
+
+        let csv = dir.join("torrents.csv");
+        let rows: Vec<String> = [good.clone(), wrong.clone(), "c".repeat(40), "d".repeat(40)]
+            .iter()
+            .map(|hash| format!("{};Name;5;0;1;0;0;0", hash))
+            .collect();
+        fs::write(&csv, format!("{}\n{}\n", CSV_HEADER, rows.join("\n"))).unwrap();
+
+        let report = verify_against_torrent_dir(csv.to_str().unwrap(), dir.to_str().unwrap(), ';').unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(good.len(), 40);
+        assert_eq!((report.checked, report.matched, report.missing), (3, 1, 1));
+        assert_eq!(report.mismatched, vec![(wrong, good)]);
+        assert_eq!(report.unreadable, vec!["c".repeat(40)]);
+    }
+
+    #[test]
+    fn test_infohash_of_non_canonical_info() {
+        // "name" antes de "length": reordenarlo daría otro hash
+        let info = b"d4:name5:a.txt6:lengthi5ee";
+        let torrent = [&b"d8:announce3:url4:info"[..], info, b"e"].concat();
+
+        assert_eq!(torrent_file_infohash(&torrent), Some(sha1_smol::Sha1::from(info).digest().to_string()));
+        assert_ne!(torrent_file_infohash(&torrent), torrent_file_infohash(&Bencode::decode(&torrent).unwrap().encode()));
+    }
+}
@@ -1,280 +1,576 @@
 use std::fs;
-use std::net::UdpSocket;
-use std::time::Duration;
-use std::collections::HashMap;
+use std::io::BufRead;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
 use std::thread;
-
-const TRACKERS: &[&str] = &[
-    "tracker.opentrackr.org:1337",
-    "open.stealth.si:80",
-    "tracker.torrent.eu.org:451",
-    "exodus.desync.com:6969",
-    "tracker.moeking.me:6969",
-    "opentracker.i2p.rocks:6969",
-    "tracker.bitsearch.to:1337",
-    "tracker.tiny-vps.com:6969",
-    "tracker.openbittorrent.com:6969",
-];
+use torrent_search::{
+    count_alive_trackers, csv_header, csv_separator, csv_text, doh_url_from_setting, is_valid_infohash,
+    join_csv_fields, load_trackers, parse_port_range, parse_trackers, print_progress, probe_trackers, process_batch,
+    proxy_from_env, prune_tracker_lines, read_csv_to_string, resolve_trackers_doh, scrape_infohashes,
+    split_csv_line, validate_csv_header, write_csv_file, AdaptiveTimeouts, ChunkSizes, CircuitBreakers, DhtOpts,
+    NetOpts, ProgressUpdate, RowResult, ScrapeOpts, ScrapeRecord, SocketSemaphore, TrackerHealth, CSV_COLUMNS,
+    CSV_HEADER, DEFAULT_BREAKER_THRESHOLD, DEFAULT_CSV_SEP, DEFAULT_TRACKER_TIMEOUT_INITIAL_MS,
+    DEFAULT_TRACKER_TIMEOUT_MAX_MS, DEFAULT_TRACKER_TIMEOUT_MIN_MS, MAX_HASHES_PER_SCRAPE,
+};
 
 const BATCH_SIZE: usize = 50;
-const TIMEOUT_SECS: u64 = 5;
 const PARALLEL_BATCHES: usize = 10; // Procesar 10 batches simultáneamente
+// Caída de seeders a partir de la cual un torrent se marca como "dying"
+const DEFAULT_DYING_DELTA: i64 = 50;
+const DEFAULT_MAX_OPEN_SOCKETS: usize = 64;
+const LAST_RESULT_COLUMN: &str = "last_result";
+const PEERS_COLUMN: &str = "peers";
 
-#[derive(Debug, Clone)]
-struct TorrentStats {
-    seeders: u32,
-    leechers: u32,
-}
-
-#[derive(Debug, Clone)]
-struct CsvRecord {
-    infohash: String,
-    name: String,
-    size_bytes: String,
-    created_unix: String,
-    seeders: u32,
-    leechers: u32,
-    completed: String,
-    scraped_date: i64,
+// SHARD=k/n (k de 1 a n): cada instancia procesa solo las filas cuyo infohash
+// cae en su parte. Los infohashes son uniformes, así que basta con los primeros
+// 8 dígitos hex módulo n.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Shard {
+    index: u32,
+    count: u32,
 }
 
-impl CsvRecord {
-    fn from_line(line: &str) -> Option<Self> {
-        let parts: Vec<&str> = line.split(';').collect();
-        if parts.len() < 8 {
-            return None;
+impl Shard {
+    fn parse(value: &str) -> Option<Self> {
+        let (k, n) = value.trim().split_once('/')?;
+        let (k, n) = (k.trim().parse::<u32>().ok()?, n.trim().parse::<u32>().ok()?);
+        (k >= 1 && k <= n).then(|| Shard { index: k - 1, count: n })
+    }
+    
+    // Las filas sin prefijo hex van siempre al shard 1, así ninguna se pierde
+    fn contains(&self, infohash: &str) -> bool {
+        let value = infohash
+            .get(..8)
+            .filter(|prefix| prefix.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|prefix| u32::from_str_radix(prefix, 16).ok());
+        match value {
+            Some(value) => value % self.count == self.index,
+            None => self.index == 0,
         }
-        
-        Some(Self {
-            infohash: parts[0].to_string(),
-            name: parts[1].to_string(),
-            size_bytes: parts[2].to_string(),
-            created_unix: parts[3].to_string(),
-            seeders: parts[4].parse().unwrap_or(0),
-            leechers: parts[5].parse().unwrap_or(0),
-            completed: parts[6].to_string(),
-            scraped_date: parts[7].parse().unwrap_or(0),
-        })
     }
     
-    fn to_line(&self) -> String {
-        format!(
-            "{};{};{};{};{};{};{};{}",
-            self.infohash,
-            self.name,
-            self.size_bytes,
-            self.created_unix,
-            self.seeders,
-            self.leechers,
-            self.completed,
-            self.scraped_date
-        )
+    // shard-1-of-4_torrents_part_3.csv junto al original; el prefijo evita que
+    // el descubrimiento de torrents_part_*.csv lo vuelva a procesar
+    fn output_path(&self, csv_path: &Path) -> PathBuf {
+        let file_name = csv_path.file_name().unwrap_or_default().to_string_lossy();
+        csv_path.with_file_name(format!("shard-{}-of-{}_{}", self.index + 1, self.count, file_name))
     }
 }
 
-// Protocolo UDP Tracker
-fn scrape_udp_tracker(tracker: &str, infohashes: &[Vec<u8>]) -> HashMap<String, TorrentStats> {
-    let mut results = HashMap::new();
-    
-    let socket = match UdpSocket::bind("0.0.0.0:0") {
-        Ok(s) => s,
-        Err(_) => return results,
+// Con DOH=1 (o cloudflare, google, una URL) los hostnames de los trackers se
+// resuelven una vez por DNS-over-HTTPS, saltándose un DNS envenenado
+fn resolve_trackers(trackers: &[String]) -> std::io::Result<HashMap<String, SocketAddr>> {
+    let Ok(value) = std::env::var("DOH") else {
+        return Ok(HashMap::new());
     };
-    
-    if socket.set_read_timeout(Some(Duration::from_secs(TIMEOUT_SECS))).is_err() {
-        return results;
+    if value == "0" {
+        return Ok(HashMap::new());
     }
+    let doh_url = doh_url_from_setting(&value).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid DOH '{}', expected 1, cloudflare, google or an https:// URL", value),
+        )
+    })?;
     
-    if socket.connect(tracker).is_err() {
-        return results;
+    let runtime = tokio::runtime::Runtime::new()?;
+    Ok(runtime.block_on(resolve_trackers_doh(trackers, &doh_url)))
+}
+
+// Subcomando check-trackers: solo el handshake connect, sin consultar hashes
+fn check_trackers(trackers: &[String], net: &NetOpts) {
+    let width = trackers.iter().map(|t| t.len()).max().unwrap_or(0).max("TRACKER".len());
+    println!("{:<width$}  {:<8}  RTT", "TRACKER", "STATUS", width = width);
+    
+    for (tracker, health) in trackers.iter().zip(probe_trackers(trackers, net)) {
+        let (status, detail) = match health {
+            TrackerHealth::Ok(rtt) => ("ok", format!("{} ms", rtt.as_millis())),
+            TrackerHealth::Timeout => ("timeout", "-".to_string()),
+            TrackerHealth::Error(e) => ("error", e),
+        };
+        println!("{:<width$}  {:<8}  {}", tracker, status, detail, width = width);
     }
+}
+
+// Primer nombre libre para la copia: {file}.bak, {file}.bak.1, ...
+fn free_backup_path(path: &str) -> String {
+    let mut backup = format!("{}.bak", path);
+    let mut n = 0;
+    while Path::new(&backup).exists() {
+        n += 1;
+        backup = format!("{}.bak.{}", path, n);
+    }
+    backup
+}
+
+// Subcomando prune-trackers: reescribe el archivo solo con los trackers que
+// contestan al connect, dejando el original en {file}.bak. Si no contesta
+// ninguno lo más probable es que no haya red, y sin `force` no se toca nada.
+fn prune_trackers(path: &str, force: bool, net: &NetOpts) -> std::io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let mut seen = HashSet::new();
+    let trackers: Vec<String> = parse_trackers(&content).0.into_iter().filter(|t| seen.insert(t.clone())).collect();
     
-    // 1. Connect request
-    let transaction_id: u32 = rand::random();
-    let mut connect_req = Vec::new();
-    connect_req.extend_from_slice(&0x41727101980u64.to_be_bytes());
-    connect_req.extend_from_slice(&0u32.to_be_bytes());
-    connect_req.extend_from_slice(&transaction_id.to_be_bytes());
+    let mut dead = HashSet::new();
+    for (tracker, health) in trackers.iter().zip(probe_trackers(&trackers, net)) {
+        let reason = match health {
+            TrackerHealth::Ok(_) => continue,
+            TrackerHealth::Timeout => "timeout".to_string(),
+            TrackerHealth::Error(e) => e,
+        };
+        println!("  - Removing {} ({})", tracker, reason);
+        dead.insert(tracker.clone());
+    }
     
-    if socket.send(&connect_req).is_err() {
-        return results;
+    if dead.is_empty() {
+        println!("All {} trackers answered, {} left unchanged", trackers.len(), path);
+        return Ok(());
     }
+    if dead.len() == trackers.len() && !force {
+        return Err(std::io::Error::other(format!(
+            "no tracker answered (is the network down?), {} left unchanged; use --force to remove them all",
+            path
+        )));
+    }
+    let backup = free_backup_path(path);
+    fs::copy(path, &backup)?;
+    fs::write(path, prune_tracker_lines(&content, &dead))?;
+    println!(
+        "Removed {} of {} trackers from {} ({} kept, original in {})",
+        dead.len(),
+        trackers.len(),
+        path,
+        trackers.len() - dead.len(),
+        backup
+    );
+    Ok(())
+}
+
+fn report_tripped_trackers(net: &NetOpts) {
+    let tripped = net.breakers.tripped();
+    if !tripped.is_empty() {
+        println!("⚡ Stopped contacting {} unresponsive trackers: {}", tripped.len(), tripped.join(", "));
+    }
+}
+
+// Lee infohashes v1 (40 hex) de una línea cada uno; el resto se avisa y se ignora
+fn read_infohashes(reader: impl BufRead) -> Vec<String> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let hash = line.trim().to_lowercase();
+            if is_valid_infohash(&hash) {
+                Some(hash)
+            } else {
+                eprintln!("Warning: skipping invalid infohash: {}", line.trim());
+                None
+            }
+        })
+        .collect()
+}
+
+// Modo filtro: infohashes por stdin, "infohash seeders leechers completed" por stdout
+fn scrape_stdin(opts: &ScrapeOpts) -> std::io::Result<()> {
+    use std::io::Write;
     
-    let mut buf = [0u8; 16];
-    let connection_id = match socket.recv(&mut buf) {
-        Ok(16) => {
-            let recv_action = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
-            let recv_trans = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
-            
-            if recv_action != 0 || recv_trans != transaction_id {
-                return results;
+    let hashes = read_infohashes(std::io::stdin().lock());
+    let mut stdout = std::io::stdout().lock();
+    
+    for chunk in hashes.chunks(BATCH_SIZE) {
+        let best = scrape_infohashes(chunk, opts);
+        
+        for hash in chunk {
+            match best.get(hash) {
+                Some(stats) => writeln!(stdout, "{} {} {} {}", hash, stats.seeders, stats.leechers, stats.completed)?,
+                None => eprintln!("Warning: no tracker answered for {}", hash),
             }
-            
-            u64::from_be_bytes([buf[8], buf[9], buf[10], buf[11], buf[12], buf[13], buf[14], buf[15]])
         }
-        _ => return results,
-    };
+        stdout.flush()?;
+    }
     
-    // 2. Scrape request
-    let scrape_trans_id: u32 = rand::random();
-    let mut scrape_req = Vec::new();
-    scrape_req.extend_from_slice(&connection_id.to_be_bytes());
-    scrape_req.extend_from_slice(&2u32.to_be_bytes());
-    scrape_req.extend_from_slice(&scrape_trans_id.to_be_bytes());
+    Ok(())
+}
+
+// Historial opcional (HISTORY_FILE): una fila por infohash y consulta, solo se
+// añade. Usa el mismo separador que los CSV (CSV_SEP).
+fn append_history(path: &str, lines: &[String], sep: char) -> std::io::Result<()> {
+    append_report(path, &["infohash", "timestamp", "seeders", "leechers"].join(&sep.to_string()), lines)
+}
+
+fn history_line(infohash: &str, now: i64, seeders: u32, leechers: u32, sep: char) -> String {
+    join_csv_fields(&[infohash.to_string(), now.to_string(), seeders.to_string(), leechers.to_string()], sep)
+}
+
+// Informe opcional de variación de seeders (DELTA_FILE); "dying" marca las
+// caídas de al menos DYING_DELTA seeders. Usa el separador de los CSV (CSV_SEP).
+fn append_deltas(path: &str, lines: &[String], sep: char) -> std::io::Result<()> {
+    let header = ["infohash", "name", "timestamp", "previous_seeders", "seeders", "delta", "dying"];
+    append_report(path, &header.join(&sep.to_string()), lines)
+}
+
+fn delta_line(infohash: &str, name: &str, now: i64, previous: u32, current: u32, dying_delta: i64, sep: char) -> String {
+    let delta = current as i64 - previous as i64;
+    join_csv_fields(&[
+        infohash.to_string(),
+        name.to_string(),
+        now.to_string(),
+        previous.to_string(),
+        current.to_string(),
+        delta.to_string(),
+        (delta <= -dying_delta).to_string(),
+    ], sep)
+}
+
+// Añade líneas a un informe CSV, escribiendo la cabecera si el archivo es nuevo
+fn append_report(path: &str, header: &str, lines: &[String]) -> std::io::Result<()> {
+    use std::io::Write;
     
-    let chunk_size = 74.min(infohashes.len());
-    for hash in &infohashes[..chunk_size] {
-        scrape_req.extend_from_slice(hash);
+    if lines.is_empty() {
+        return Ok(());
     }
     
-    if socket.send(&scrape_req).is_err() {
-        return results;
+    let is_new = !std::path::Path::new(path).exists();
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    if is_new {
+        writeln!(file, "{}", header)?;
     }
-    
-    // 3. Leer respuesta
-    let mut response = vec![0u8; 2048];
-    if let Ok(n) = socket.recv(&mut response) {
-        if n >= 8 {
-            let recv_action = u32::from_be_bytes([response[0], response[1], response[2], response[3]]);
-            let recv_trans = u32::from_be_bytes([response[4], response[5], response[6], response[7]]);
-            
-            if recv_action == 2 && recv_trans == scrape_trans_id {
-                let mut offset = 8;
-                for hash in &infohashes[..chunk_size] {
-                    if offset + 12 <= n {
-                        let seeders = u32::from_be_bytes([
-                            response[offset],
-                            response[offset + 1],
-                            response[offset + 2],
-                            response[offset + 3],
-                        ]);
-                        let leechers = u32::from_be_bytes([
-                            response[offset + 8],
-                            response[offset + 9],
-                            response[offset + 10],
-                            response[offset + 11],
-                        ]);
-                        
-                        let hash_str = hex::encode(hash).to_lowercase();
-                        results.insert(hash_str, TorrentStats { seeders, leechers });
-                        
-                        offset += 12;
-                    }
-                }
-            }
-        }
+    for line in lines {
+        writeln!(file, "{}", line)?;
     }
     
-    results
+    Ok(())
 }
 
-fn scrape_all_trackers_parallel(infohashes: &[Vec<u8>]) -> Vec<HashMap<String, TorrentStats>> {
-    let results = Arc::new(Mutex::new(Vec::new()));
-    let mut handles = vec![];
-    
-    for tracker in TRACKERS {
-        let tracker = tracker.to_string();
-        let infohashes = infohashes.to_vec();
-        let results = Arc::clone(&results);
-        
-        let handle = thread::spawn(move || {
-            if let Ok(tracker_results) = std::panic::catch_unwind(|| {
-                scrape_udp_tracker(&tracker, &infohashes)
-            }) {
-                if !tracker_results.is_empty() {
-                    results.lock().unwrap().push(tracker_results);
-                }
-            }
-        });
-        
-        handles.push(handle);
+// Las columnas opcionales van siempre detrás de todas las de CSV_HEADER. En un
+// archivo antiguo (sin files_count o source) se completan antes la cabecera y
+// las filas; si no, last_result o peers se leerían como esas columnas.
+fn upgrade_legacy_layout(header: &mut String, data_lines: &mut [String], sep: char) {
+    let columns = split_csv_line(header, sep).len();
+    let full = CSV_HEADER.split(DEFAULT_CSV_SEP).count();
+    if columns >= full {
+        return;
     }
     
-    // Esperar a que todos los threads terminen
-    for handle in handles {
-        let _ = handle.join();
+    *header = csv_header(sep);
+    for line in data_lines {
+        let fields = split_csv_line(line, sep).len();
+        if (CSV_COLUMNS.len()..full).contains(&fields) {
+            line.push_str(&sep.to_string().repeat(full - fields));
+        }
     }
-    
-    let final_results = results.lock().unwrap().clone();
-    final_results
 }
 
-fn process_batch(
-    batch_indices: Vec<usize>,
-    batch_hashes: Vec<String>,
+// Infohash de una fila (primera columna), respetando las comillas
+fn row_infohash(line: &str, sep: char) -> String {
+    split_csv_line(line, sep).into_iter().next().unwrap_or_default()
+}
+
+// Posición de una columna opcional (last_result, peers) en la cabecera,
+// añadiéndola al final si no existe
+fn optional_column(header: &mut String, name: &str, sep: char) -> usize {
+    let columns = split_csv_line(header, sep);
+    columns.iter().position(|c| c == name).unwrap_or_else(|| {
+        header.push(sep);
+        header.push_str(name);
+        columns.len()
+    })
+}
+
+// Espera a que los batches de un chunk suelten su extremo del canal (al
+// terminar o al hacer panic). Devuelve false si se llega antes a `deadline`,
+// con hilos aún vivos.
+fn wait_for_batches(done: &mpsc::Receiver<()>, deadline: Option<Instant>) -> bool {
+    loop {
+        let result = match deadline {
+            Some(deadline) => done.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => done.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match result {
+            Ok(()) => continue,
+            Err(RecvTimeoutError::Disconnected) => return true,
+            Err(RecvTimeoutError::Timeout) => return false,
+        }
+    }
+}
+
+// DELTA_CSV=1: solo las filas cuyos seeders o leechers cambiaron, con el
+// valor anterior y el nuevo, en {archivo}.delta.csv
+const DELTA_CSV_COLUMNS: [&str; 6] = ["infohash", "name", "old_seeders", "seeders", "old_leechers", "leechers"];
+
+fn delta_csv_path(csv_path: &Path) -> PathBuf {
+    let file_name = csv_path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = file_name.strip_suffix(".gz").unwrap_or(&file_name);
+    let stem = stem.strip_suffix(".csv").unwrap_or(stem);
+    csv_path.with_file_name(format!("{}.delta.csv", stem))
+}
+
+// Filas del delta en el orden del archivo. Las muertas (None) y las que no se
+// llegaron a consultar no cuentan como cambio.
+fn changed_rows(
     data_lines: &[String],
-) -> Vec<(usize, Option<CsvRecord>)> {
-    let hash_bytes: Vec<Vec<u8>> = batch_hashes
+    updated: &HashMap<usize, Option<ScrapeRecord>>,
+    unchanged: impl Fn(usize) -> bool,
+    sep: char,
+) -> Vec<String> {
+    let mut indices: Vec<usize> = updated.keys().copied().filter(|&i| !unchanged(i)).collect();
+    indices.sort_unstable();
+    
+    indices
+        .into_iter()
+        .filter_map(|i| {
+            let new = updated[&i].as_ref()?;
+            let old = ScrapeRecord::from_line(&data_lines[i], sep)?;
+            if (old.seeders, old.leechers) == (new.seeders, new.leechers) {
+                return None;
+            }
+            Some(join_csv_fields(&[
+                old.infohash,
+                old.name,
+                old.seeders.to_string(),
+                new.seeders.to_string(),
+                old.leechers.to_string(),
+                new.leechers.to_string(),
+            ], sep))
+        })
+        .collect()
+}
+
+// Filas cuyo infohash no son 40 hex: no se consultan y se escriben sin cambios
+fn invalid_hash_rows(data_lines: &[String], sep: char) -> HashSet<usize> {
+    data_lines
         .iter()
-        .filter_map(|h| hex::decode(h).ok())
-        .filter(|b| b.len() == 20)
-        .collect();
+        .enumerate()
+        .filter(|(_, line)| !is_valid_infohash(&row_infohash(line, sep)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Reconstruye el archivo en el orden de entrada: cada fila superviviente sale
+// en su posición original (actualizada o sin cambios) y las muertas se quitan
+// sin mover las demás, así dos scrapes seguidos se comparan bien con git diff.
+fn rebuild_lines(
+    header: &str,
+    data_lines: &[String],
+    updated: &HashMap<usize, Option<ScrapeRecord>>,
+    unchanged: impl Fn(usize) -> bool,
+    skipped: impl Fn(usize) -> bool,
+    sep: char,
+) -> Vec<String> {
+    let mut lines = vec![header.to_string()];
+    for (i, line) in data_lines.iter().enumerate() {
+        if skipped(i) {
+            continue;
+        }
+        if unchanged(i) {
+            lines.push(line.clone());
+        } else if let Some(Some(record)) = updated.get(&i) {
+            lines.push(record.to_line(sep));
+        }
+    }
+    lines
+}
+
+fn main() -> std::io::Result<()> {
+    let data_dir = std::env::var("DATA_DIR").unwrap_or_else(|_| ".".to_string());
     
-    if hash_bytes.is_empty() {
-        return batch_indices
-            .iter()
-            .map(|&idx| {
-                let record = CsvRecord::from_line(&data_lines[idx]);
-                (idx, record)
-            })
-            .collect();
+    // Máximo de sockets UDP abiertos simultáneamente (evita "Too many open files")
+    let max_open_sockets = std::env::var("MAX_OPEN_SOCKETS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_OPEN_SOCKETS);
+    let (trackers, tracker_options) = load_trackers()?;
+    
+    // Solo las peticiones HTTP (DoH) usan el proxy; el scrape UDP sale directo
+    if let Some(var) = proxy_from_env()? {
+        eprintln!("Warning: {} only applies to HTTP requests, UDP tracker scrapes bypass the proxy", var);
     }
     
-    // Consultar todos los trackers EN PARALELO
-    let all_results = scrape_all_trackers_parallel(&hash_bytes);
+    // Rango de puertos UDP de origen; un valor inválido es un error de configuración
+    let port_range = match std::env::var("UDP_PORT_RANGE") {
+        Ok(value) => Some(parse_port_range(&value).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid UDP_PORT_RANGE '{}', expected e.g. 49152-49200", value),
+            )
+        })?),
+        Err(_) => None,
+    };
     
-    // Consolidar resultados
-    let mut final_results = Vec::new();
+    // Límites del timeout adaptativo por tracker, en milisegundos
+    let env_ms = |name: &str, default: u64| {
+        Duration::from_millis(
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default),
+        )
+    };
+    let net = NetOpts {
+        port_range,
+        timeouts: Arc::new(AdaptiveTimeouts::new(
+            env_ms("TRACKER_TIMEOUT_INITIAL_MS", DEFAULT_TRACKER_TIMEOUT_INITIAL_MS),
+            env_ms("TRACKER_TIMEOUT_MIN_MS", DEFAULT_TRACKER_TIMEOUT_MIN_MS),
+            env_ms("TRACKER_TIMEOUT_MAX_MS", DEFAULT_TRACKER_TIMEOUT_MAX_MS),
+        )),
+        // SCRAPE_CHUNK_SIZE cambia el valor por defecto; chunk=N en TRACKERS_FILE, el de un tracker
+        chunk_sizes: Arc::new(ChunkSizes::new(
+            std::env::var("SCRAPE_CHUNK_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(MAX_HASHES_PER_SCRAPE),
+            tracker_options
+                .iter()
+                .filter_map(|(tracker, options)| options.chunk_size.map(|size| (tracker.clone(), size)))
+                .collect(),
+        )),
+        resolved: Arc::new(resolve_trackers(&trackers)?),
+        connection_ttl: None,
+        // TRACKER_BREAKER_THRESHOLD=0 desactiva el corte; sin COOLDOWN dura toda la ejecución
+        breakers: Arc::new(CircuitBreakers::new(
+            std::env::var("TRACKER_BREAKER_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_BREAKER_THRESHOLD),
+            std::env::var("TRACKER_BREAKER_COOLDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs),
+        )),
+        transaction_ids: None,
+        // Trackers marcados con "announce" en TRACKERS_FILE
+        announce_first: Arc::new(
+            tracker_options
+                .iter()
+                .filter(|(_, options)| options.announce)
+                .map(|(tracker, _)| tracker.clone())
+                .collect(),
+        ),
+        cancel: Arc::default(),
+    };
     
-    for (i, &line_idx) in batch_indices.iter().enumerate() {
-        if i >= batch_hashes.len() {
-            break;
-        }
-        
-        let hash = batch_hashes[i].to_lowercase();
-        let mut max_seeders = 0u32;
-        let mut max_leechers = 0u32;
-        let mut any_success = false;
-        
-        for tracker_result in &all_results {
-            if let Some(stats) = tracker_result.get(&hash) {
-                max_seeders = max_seeders.max(stats.seeders);
-                max_leechers = max_leechers.max(stats.leechers);
-                any_success = true;
-            }
-        }
-        
-        let original_line = &data_lines[line_idx];
-        
-        if !any_success {
-            if let Some(record) = CsvRecord::from_line(original_line) {
-                final_results.push((line_idx, Some(record)));
-            }
-        } else if max_seeders > 0 || max_leechers > 0 {
-            if let Some(mut record) = CsvRecord::from_line(original_line) {
-                record.seeders = max_seeders;
-                record.leechers = max_leechers;
-                record.scraped_date = chrono::Utc::now().timestamp();
-                final_results.push((line_idx, Some(record)));
-            }
-        } else {
-            final_results.push((line_idx, None));
+    if std::env::args().nth(1).as_deref() == Some("check-trackers") {
+        check_trackers(&trackers, &net);
+        return Ok(());
+    }
+    if std::env::args().nth(1).as_deref() == Some("prune-trackers") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        let force = args.iter().any(|arg| arg == "--force");
+        let path = args.iter().find(|arg| !arg.starts_with("--")).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "usage: tracker-scraper prune-trackers [--force] <file>",
+            )
+        })?;
+        return prune_trackers(path, force, &net);
+    }
+    
+    let opts = ScrapeOpts {
+        trackers: Arc::new(trackers),
+        socket_limit: Arc::new(SocketSemaphore::new(max_open_sockets)),
+        net,
+        csv_sep: csv_separator(),
+        // Algunos trackers dan cifras de completed absurdas; con 0 no se tocan
+        update_completed: std::env::var("UPDATE_COMPLETED").map(|v| v != "0").unwrap_or(true),
+        // Búsqueda DHT para rescatar torrents que los trackers dan por muertos; es lenta
+        dht: std::env::var("USE_DHT").is_ok_and(|v| v == "1").then(|| Arc::new(DhtOpts::default())),
+        on_progress: Arc::new(print_progress),
+    };
+    
+    // Con la red caída todo parecería muerto: mejor abortar antes de tocar ningún CSV
+    if let Ok(value) = std::env::var("MIN_TRACKERS_ALIVE") {
+        let min_alive: usize = value.trim().parse().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid MIN_TRACKERS_ALIVE '{}', expected a number", value),
+            )
+        })?;
+        let alive = count_alive_trackers(&opts.trackers, &opts.net);
+        if alive < min_alive {
+            return Err(std::io::Error::other(format!(
+                "only {} of {} trackers answered, MIN_TRACKERS_ALIVE is {}; aborting",
+                alive,
+                opts.trackers.len(),
+                min_alive
+            )));
         }
+        println!("{} of {} trackers alive", alive, opts.trackers.len());
     }
     
-    final_results
-}
-
-fn main() -> std::io::Result<()> {
-    let current_dir = std::env::current_dir()?;
+    if std::env::args().nth(1).as_deref() == Some("scrape-stdin") {
+        return scrape_stdin(&opts);
+    }
+    
+    // Con SHARD el CSV original no se toca: cada instancia escribe su parte aparte
+    let shard = match std::env::var("SHARD") {
+        Ok(value) => Some(Shard::parse(&value).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid SHARD '{}', expected k/n with 1 <= k <= n", value),
+            )
+        })?),
+        Err(_) => None,
+    };
+    
+    // Serie temporal de seeders/leechers, desactivada por defecto
+    let history_file = std::env::var("HISTORY_FILE").ok();
+    
+    // Variación de seeders respecto al CSV, también opcional
+    let delta_file = std::env::var("DELTA_FILE").ok();
+    let dying_delta = std::env::var("DYING_DELTA")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_DYING_DELTA);
     
-    let mut files: Vec<_> = fs::read_dir(&current_dir)?
+    // DELTA_CSV=1 escribe los cambios aparte y deja el CSV original intacto,
+    // salvo que también se pida DELTA_IN_PLACE=1
+    let delta_csv = std::env::var("DELTA_CSV").is_ok_and(|v| v == "1");
+    let rewrite_in_place = !delta_csv || std::env::var("DELTA_IN_PLACE").is_ok_and(|v| v == "1");
+    
+    // MAX_ROWS=100: solo se consultan las primeras filas de cada archivo, para
+    // pruebas rápidas; el resto se escribe sin cambios
+    let max_rows: Option<usize> = std::env::var("MAX_ROWS").ok().and_then(|v| v.parse().ok());
+    
+    // MARK_FAILED=1: columna last_result con "ok" o "failed" (ningún tracker
+    // contestó y se conservan los datos anteriores) en cada fila consultada
+    let mark_failed = std::env::var("MARK_FAILED").is_ok_and(|v| v == "1");
+    
+    // WRITE_PEERS=1: columna peers = seeders + leechers, recalculada en cada
+    // fila consultada (las que no se consultan la mantienen como estaba)
+    let write_peers = std::env::var("WRITE_PEERS").is_ok_and(|v| v == "1");
+    
+    // FILE_TIMEOUT_SECS: tiempo máximo por archivo. Al agotarse se guarda lo
+    // consultado hasta el último chunk completo y se pasa al siguiente archivo.
+    let file_timeout = std::env::var("FILE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
+    
+    // Ctrl-C: terminar el chunk en curso, guardar lo obtenido y salir
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || {
+            eprintln!("\n⚠️  Interrupted, saving partial results...");
+            interrupted.store(true, Ordering::SeqCst);
+        })
+        .map_err(std::io::Error::other)?;
+    }
+    
+    let mut files: Vec<_> = fs::read_dir(&data_dir)?
         .filter_map(|e| e.ok())
         .filter(|e| {
             let name = e.file_name();
             let name_str = name.to_string_lossy();
-            name_str.starts_with("torrents_part_") && name_str.ends_with(".csv")
+            name_str.starts_with("torrents_part_")
+                && (name_str.ends_with(".csv") || name_str.ends_with(".csv.gz"))
         })
         .collect();
     
@@ -293,7 +589,7 @@ fn main() -> std::io::Result<()> {
     });
     
     if files.is_empty() {
-        println!("No torrents_part_*.csv files found.");
+        println!("No torrents_part_*.csv(.gz) files found in {}.", data_dir);
         return Ok(());
     }
     
@@ -303,46 +599,106 @@ fn main() -> std::io::Result<()> {
         
         println!("\n📦 Processing {}...", file_name);
         
-        let content = fs::read_to_string(&csv_path)?;
-        let lines: Vec<String> = content.lines().map(String::from).collect();
+        let content = read_csv_to_string(&csv_path)?;
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
         
         if lines.len() <= 1 {
             continue;
         }
         
-        let header = &lines[0];
+        let mut header = lines[0].clone();
+        if let Err(e) = validate_csv_header(&header, opts.csv_sep) {
+            eprintln!("Skipping {}: {}", file_name, e);
+            continue;
+        }
+        if mark_failed || write_peers {
+            upgrade_legacy_layout(&mut header, &mut lines[1..], opts.csv_sep);
+        }
+        let result_column = mark_failed.then(|| optional_column(&mut header, LAST_RESULT_COLUMN, opts.csv_sep));
+        let peers_column = write_peers.then(|| optional_column(&mut header, PEERS_COLUMN, opts.csv_sep));
+        
         let data_lines = &lines[1..];
         let total = data_lines.len();
         
+        // Filas con columnas de menos: no se consultan y se escriben sin cambios
+        let malformed: HashSet<usize> = data_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| split_csv_line(line, opts.csv_sep).len() < CSV_COLUMNS.len())
+            .map(|(i, _)| i)
+            .collect();
+        for &i in &malformed {
+            eprintln!("Skipping malformed row {} in {}", i + 2, file_name);
+        }
+        if !malformed.is_empty() {
+            println!("Skipped {} malformed rows", malformed.len());
+        }
+        
+        // Filas de otros shards: ni se consultan ni se escriben
+        let other_shards: HashSet<usize> = match shard {
+            Some(shard) => data_lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| !shard.contains(&row_infohash(line, opts.csv_sep).to_lowercase()))
+                .map(|(i, _)| i)
+                .collect(),
+            None => HashSet::new(),
+        };
+        let limit = max_rows.map_or(total, |max| max.min(total));
+        
+        // Infohashes que no son 40 hex: no se pueden consultar
+        let invalid = invalid_hash_rows(data_lines, opts.csv_sep);
+        let invalid_hashes = (0..limit)
+            .filter(|i| !malformed.contains(i) && !other_shards.contains(i) && invalid.contains(i))
+            .count();
+        let to_check = (0..limit)
+            .filter(|i| !malformed.contains(i) && !other_shards.contains(i) && !invalid.contains(i))
+            .count();
+        if invalid_hashes > 0 {
+            eprintln!("Warning: {} rows in {} have an invalid infohash and won't be scraped", invalid_hashes, file_name);
+        }
+        
         println!(
-            "Checking {} torrents (Batch: {}, Parallel batches: {}, Trackers in parallel: {})...",
-            total, BATCH_SIZE, PARALLEL_BATCHES, TRACKERS.len()
+            "Checking {} torrents (Batch: {}, Parallel batches: {}, Trackers in parallel: {}, Max sockets: {})...",
+            to_check, BATCH_SIZE, PARALLEL_BATCHES, opts.trackers.len(), max_open_sockets
         );
         
         let updated_records = Arc::new(Mutex::new(HashMap::new()));
-        let stats = Arc::new(Mutex::new((0, 0, 0, 0))); // processed, alive, dead, failed
+        let stats = Arc::new(Mutex::new(ProgressUpdate { total: to_check, ..Default::default() }));
+        let history_lines = Arc::new(Mutex::new(Vec::new()));
+        let delta_lines = Arc::new(Mutex::new(Vec::new()));
+        let dying = Arc::new(Mutex::new(0usize));
+        
+        // Filas a partir de este índice no se llegaron a consultar
+        let mut scraped_until = limit;
+        let deadline = file_timeout.map(|timeout| Instant::now() + timeout);
+        let mut timed_out = false;
         
         // Procesar múltiples batches en paralelo
-        for chunk_start in (0..total).step_by(BATCH_SIZE * PARALLEL_BATCHES) {
-            let mut batch_handles = vec![];
+        for chunk_start in (0..limit).step_by(BATCH_SIZE * PARALLEL_BATCHES) {
+            timed_out = deadline.is_some_and(|d| Instant::now() >= d);
+            if interrupted.load(Ordering::SeqCst) || timed_out {
+                scraped_until = chunk_start;
+                break;
+            }
+            
+            let (done_tx, done_rx) = mpsc::channel();
             
             for batch_offset in 0..PARALLEL_BATCHES {
                 let i = chunk_start + (batch_offset * BATCH_SIZE);
-                if i >= total {
+                if i >= limit {
                     break;
                 }
                 
                 let mut batch_indices = Vec::new();
                 let mut batch_hashes = Vec::new();
                 
-                for idx in i..(i + BATCH_SIZE).min(total) {
-                    let line = &data_lines[idx];
-                    if let Some(infohash) = line.split(';').next() {
-                        if !infohash.is_empty() && infohash.len() == 40 {
-                            batch_indices.push(idx);
-                            batch_hashes.push(infohash.to_string());
-                        }
+                for (idx, line) in data_lines.iter().enumerate().take((i + BATCH_SIZE).min(limit)).skip(i) {
+                    if malformed.contains(&idx) || other_shards.contains(&idx) || invalid.contains(&idx) {
+                        continue;
                     }
+                    batch_indices.push(idx);
+                    batch_hashes.push(row_infohash(line, opts.csv_sep));
                 }
                 
                 if batch_hashes.is_empty() {
@@ -352,57 +708,363 @@ fn main() -> std::io::Result<()> {
                 let data_lines_clone: Vec<String> = data_lines.to_vec();
                 let updated_records = Arc::clone(&updated_records);
                 let stats = Arc::clone(&stats);
+                let opts = opts.clone();
                 
-                let handle = thread::spawn(move || {
-                    let batch_results = process_batch(batch_indices, batch_hashes, &data_lines_clone);
+                let history_lines = Arc::clone(&history_lines);
+                let record_history = history_file.is_some();
+                let delta_lines = Arc::clone(&delta_lines);
+                let dying = Arc::clone(&dying);
+                let record_deltas = delta_file.is_some();
+                
+                let done_tx = done_tx.clone();
+                thread::spawn(move || {
+                    let _done = done_tx;
+                    let batch_results = process_batch(batch_indices, batch_hashes, &data_lines_clone, &opts);
                     
                     let mut records = updated_records.lock().unwrap();
                     let mut s = stats.lock().unwrap();
+                    let mut history = history_lines.lock().unwrap();
+                    let mut deltas = delta_lines.lock().unwrap();
+                    let now = chrono::Utc::now().timestamp();
                     
-                    for (idx, record_opt) in batch_results {
-                        records.insert(idx, record_opt.clone());
-                        s.0 += 1; // processed
+                    for RowResult { idx, mut record, scraped, previous_seeders } in batch_results {
+                        if let (Some(column), Some(record)) = (result_column, record.as_mut()) {
+                            record.set_extra(column, if scraped.is_some() { "ok" } else { "failed" });
+                        }
+                        if let (Some(column), Some(record)) = (peers_column, record.as_mut()) {
+                            record.set_extra(column, &record.peers().to_string());
+                        }
                         
-                        match record_opt {
-                            Some(record) if record.seeders > 0 || record.leechers > 0 => s.1 += 1, // alive
-                            Some(_) => s.3 += 1, // failed
-                            None => s.2 += 1, // dead
+                        if let Some(scraped) = scraped.as_ref().filter(|_| record_history) {
+                            let infohash = row_infohash(&data_lines_clone[idx], opts.csv_sep).to_lowercase();
+                            history.push(history_line(&infohash, now, scraped.seeders, scraped.leechers, opts.csv_sep));
+                        }
+                        
+                        if let (Some(scraped), Some(previous)) = (&scraped, previous_seeders) {
+                            if scraped.seeders as i64 - previous as i64 <= -dying_delta {
+                                *dying.lock().unwrap() += 1;
+                            }
+                            if record_deltas {
+                                if let Some(original) = ScrapeRecord::from_line(&data_lines_clone[idx], opts.csv_sep) {
+                                    deltas.push(delta_line(
+                                        &original.infohash,
+                                        &original.name,
+                                        now,
+                                        previous,
+                                        scraped.seeders,
+                                        dying_delta,
+                                        opts.csv_sep,
+                                    ));
+                                }
+                            }
+                        }
+                        
+                        records.insert(idx, record.clone());
+                        s.processed += 1;
+                        
+                        match record {
+                            Some(record) if record.seeders > 0 || record.leechers > 0 => s.alive += 1,
+                            Some(_) => s.failed += 1,
+                            None => s.dead += 1,
                         }
                     }
                 });
-                
-                batch_handles.push(handle);
             }
+            drop(done_tx);
             
-            // Esperar a que terminen todos los batches de este chunk
-            for handle in batch_handles {
-                let _ = handle.join();
+            // Esperar a que terminen todos los batches de este chunk. Si se
+            // agota el tiempo del archivo, el chunk entero se deja sin cambios;
+            // los batches se cancelan y se espera a que suelten sus sockets para
+            // que el siguiente archivo no supere MAX_OPEN_SOCKETS.
+            if !wait_for_batches(&done_rx, deadline) {
+                opts.net.cancel.store(true, Ordering::SeqCst);
+                wait_for_batches(&done_rx, None);
+                opts.net.cancel.store(false, Ordering::SeqCst);
+                timed_out = true;
+                scraped_until = chunk_start;
+                break;
             }
             
-            let s = stats.lock().unwrap();
-            let (processed, alive, dead, failed) = *s;
-            let percent = (processed as f64 / total as f64 * 100.0).round();
-            print!(
-                "\r🚀 Progress: {:.2}% ({}/{}) | Alive: {} | Dead: {} | Failed: {}   ",
-                percent, processed, total, alive, dead, failed
+            let lines = std::mem::take(&mut *history_lines.lock().unwrap());
+            if let Some(path) = &history_file {
+                append_history(path, &lines, opts.csv_sep)?;
+            }
+            let lines = std::mem::take(&mut *delta_lines.lock().unwrap());
+            if let Some(path) = &delta_file {
+                append_deltas(path, &lines, opts.csv_sep)?;
+            }
+            
+            let update = *stats.lock().unwrap();
+            (opts.on_progress)(update);
+        }
+        
+        if timed_out {
+            eprintln!(
+                "\n⏱️  {} timed out after {}s, keeping {} rows unchecked",
+                file_name,
+                file_timeout.unwrap_or_default().as_secs(),
+                limit - scraped_until
             );
-            use std::io::Write;
-            std::io::stdout().flush().unwrap();
         }
         
-        println!("\nWriting updated {}...", file_name);
+        let dying = *dying.lock().unwrap();
+        if dying > 0 {
+            println!("\n📉 {} torrents lost {} or more seeders", dying, dying_delta);
+        }
         
-        let final_records = updated_records.lock().unwrap();
-        let mut final_lines = vec![header.clone()];
-        for i in 0..total {
-            if let Some(Some(record)) = final_records.get(&i) {
-                final_lines.push(record.to_line());
-            }
+        let out_path = match shard {
+            Some(shard) => shard.output_path(&csv_path),
+            None => csv_path.clone(),
+        };
+        let out_name = out_path.file_name().unwrap().to_string_lossy();
+        
+        if delta_csv {
+            let delta_path = delta_csv_path(&out_path);
+            let rows = changed_rows(
+                data_lines,
+                &updated_records.lock().unwrap(),
+                |i| i >= scraped_until || malformed.contains(&i) || invalid.contains(&i),
+                opts.csv_sep,
+            );
+            println!("\nWriting {} changed rows to {}...", rows.len(), delta_path.display());
+            let header = DELTA_CSV_COLUMNS.join(&opts.csv_sep.to_string());
+            let content: String = std::iter::once(header).chain(rows).map(|line| line + "\n").collect();
+            write_csv_file(&delta_path, &content)?;
         }
         
-        fs::write(&csv_path, final_lines.join("\n") + "\n")?;
+        if rewrite_in_place {
+            println!("\nWriting updated {}...", out_name);
+            
+            let final_lines = rebuild_lines(
+                &header,
+                data_lines,
+                &updated_records.lock().unwrap(),
+                |i| i >= scraped_until || malformed.contains(&i) || invalid.contains(&i),
+                |i| other_shards.contains(&i),
+                opts.csv_sep,
+            );
+            
+            write_csv_file(&out_path, &csv_text(&final_lines))?;
+        }
+        
+        if interrupted.load(Ordering::SeqCst) {
+            println!("Saved partial results to {}, exiting.", out_name);
+            report_tripped_trackers(&opts.net);
+            return Ok(());
+        }
     }
     
     println!("\n✅ All files updated.");
+    report_tripped_trackers(&opts.net);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_delta_line() {
+        assert_eq!(
+            delta_line("abc", "Some; name", 1700000000, 120, 20, 50, ';'),
+            "abc;\"Some; name\";1700000000;120;20;-100;true"
+        );
+        assert_eq!(delta_line("abc", "Name", 1700000000, 10, 15, 50, ';'), "abc;Name;1700000000;10;15;5;false");
+        assert_eq!(
+            delta_line("abc", "Some, name", 1700000000, 10, 15, 50, ','),
+            "abc,\"Some, name\",1700000000,10,15,5,false"
+        );
+    }
+    
+    #[test]
+    fn test_row_infohash_respects_quotes() {
+        let hash = "a".repeat(40);
+        assert_eq!(row_infohash(&format!("\"{}\";Name;1", hash), ';'), hash);
+        assert_eq!(row_infohash(&format!("{};\"x;y\"", hash), ';'), hash);
+        assert_eq!(row_infohash("", ';'), "");
+    }
+    
+    #[test]
+    fn test_append_history() {
+        let path = std::env::temp_dir().join(format!("history_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        
+        append_history(path, &[history_line("abc", 1700000000, 10, 2, '\t')], '\t').unwrap();
+        append_history(path, &[], '\t').unwrap();
+        append_history(path, &[history_line("def", 1700000100, 0, 1, '\t')], '\t').unwrap();
+        let content = fs::read_to_string(path).unwrap();
+        let _ = fs::remove_file(path);
+        
+        assert_eq!(
+            content,
+            "infohash\ttimestamp\tseeders\tleechers\nabc\t1700000000\t10\t2\ndef\t1700000100\t0\t1\n"
+        );
+    }
+    
+    #[test]
+    fn test_wait_for_batches() {
+        let (tx, rx) = mpsc::channel::<()>();
+        let slow = tx.clone();
+        thread::spawn(move || {
+            let _done = slow;
+            thread::sleep(Duration::from_millis(300));
+        });
+        thread::spawn(move || drop(tx));
+        
+        assert!(!wait_for_batches(&rx, Some(Instant::now() + Duration::from_millis(50))));
+        assert!(wait_for_batches(&rx, None));
+    }
+    
+    #[test]
+    fn test_optional_columns() {
+        let mut header = torrent_search::CSV_HEADER.to_string();
+        assert_eq!(optional_column(&mut header, LAST_RESULT_COLUMN, ';'), 10);
+        assert_eq!(optional_column(&mut header, PEERS_COLUMN, ';'), 11);
+        assert_eq!(optional_column(&mut header, LAST_RESULT_COLUMN, ';'), 10);
+        assert_eq!(header, format!("{};last_result;peers", torrent_search::CSV_HEADER));
+        
+        let mut record = ScrapeRecord::from_line(&format!("{};Name;1;0;3;1;0;0", "a".repeat(40)), ';').unwrap();
+        record.set_extra(10, "failed");
+        record.set_extra(11, &record.peers().to_string());
+        assert!(record.to_line(';').ends_with(";0;0;;;failed;4"));
+    }
+    
+    #[test]
+    fn test_optional_columns_on_legacy_file() {
+        let mut header = CSV_HEADER.trim_end_matches(";source").to_string();
+        let mut lines = vec![format!("{};\"Name; 2\";1;0;3;1;0;0;7", "a".repeat(40)), "short;row".to_string()];
+        upgrade_legacy_layout(&mut header, &mut lines, ';');
+        
+        assert_eq!(header, CSV_HEADER);
+        assert_eq!(lines[0], format!("{};\"Name; 2\";1;0;3;1;0;0;7;", "a".repeat(40)));
+        assert_eq!(lines[1], "short;row");
+        assert_eq!(optional_column(&mut header, LAST_RESULT_COLUMN, ';'), 10);
+        
+        let mut record = ScrapeRecord::from_line(&lines[0], ';').unwrap();
+        record.set_extra(10, "ok");
+        let record = torrent_search::TorrentCsvRecord::from_csv_line(&record.to_line(';'), ';').unwrap();
+        assert_eq!((record.files_count, record.source.as_str()), (7, ""));
+        
+        // Archivo de 8 columnas: peers queda detrás de files_count y source
+        let mut header = CSV_COLUMNS.join(";");
+        let mut lines = vec![format!("{};Name;1;0;{};1;0;0", "a".repeat(40), u32::MAX)];
+        upgrade_legacy_layout(&mut header, &mut lines, ';');
+        assert_eq!(optional_column(&mut header, PEERS_COLUMN, ';'), 10);
+        let mut record = ScrapeRecord::from_line(&lines[0], ';').unwrap();
+        record.set_extra(10, &record.peers().to_string());
+        assert!(record.to_line(';').ends_with(&format!(";;;{}", u32::MAX as u64 + 1)));
+    }
+    
+    #[test]
+    fn test_prune_trackers_keeps_file_when_none_answer() {
+        let path = std::env::temp_dir().join(format!("trackers_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "# local\n127.0.0.1:1\n").unwrap();
+        fs::write(format!("{}.bak", path), "old backup").unwrap();
+        
+        assert!(prune_trackers(path, false, &NetOpts::default()).is_err());
+        assert_eq!(fs::read_to_string(path).unwrap(), "# local\n127.0.0.1:1\n");
+        
+        prune_trackers(path, true, &NetOpts::default()).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "# local\n");
+        assert_eq!(fs::read_to_string(format!("{}.bak", path)).unwrap(), "old backup");
+        assert_eq!(fs::read_to_string(format!("{}.bak.1", path)).unwrap(), "# local\n127.0.0.1:1\n");
+        
+        for file in [path.to_string(), format!("{}.bak", path), format!("{}.bak.1", path)] {
+            let _ = fs::remove_file(file);
+        }
+    }
+    
+    #[test]
+    fn test_rebuild_lines_preserves_input_order() {
+        let data_lines: Vec<String> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|c| format!("{};{};1;0;1;0;0;0", c.repeat(40), c))
+            .collect();
+        let mut updated = HashMap::new();
+        // Se insertan desordenados, como llegan de los hilos
+        for i in [3, 0, 1] {
+            let mut record = ScrapeRecord::from_line(&data_lines[i], ';').unwrap();
+            record.seeders = 50;
+            updated.insert(i, Some(record));
+        }
+        updated.insert(2, None);
+        
+        let lines = rebuild_lines("header", &data_lines, &updated, |i| i == 4, |_| false, ';');
+        
+        let names: Vec<&str> = lines[1..].iter().map(|l| l.split(';').nth(1).unwrap()).collect();
+        assert_eq!(lines[0], "header");
+        assert_eq!(names, ["a", "b", "d", "e"]);
+        assert!(lines[1].contains(";50;"));
+        assert_eq!(lines[4], data_lines[4]);
+    }
+    
+    #[test]
+    fn test_rebuild_lines_keeps_invalid_hashes() {
+        let data_lines = vec![
+            format!("{};ok;1;0;1;0;0;0", "a".repeat(40)),
+            "not-a-hash;bad;1;0;1;0;0;0".to_string(),
+        ];
+        let mut updated = HashMap::new();
+        updated.insert(0, ScrapeRecord::from_line(&data_lines[0], ';'));
+        let invalid = invalid_hash_rows(&data_lines, ';');
+        assert_eq!(invalid, HashSet::from([1]));
+        
+        let lines = rebuild_lines("header", &data_lines, &updated, |i| invalid.contains(&i), |_| false, ';');
+        
+        assert_eq!(lines[1..], data_lines[..]);
+    }
+    
+    #[test]
+    fn test_changed_rows() {
+        let data_lines: Vec<String> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|c| format!("{};{};1;0;5;2;0;0", c.repeat(40), c))
+            .collect();
+        let mut updated = HashMap::new();
+        for (i, seeders, leechers) in [(2, 5, 7), (0, 9, 2), (1, 5, 2), (3, 1, 1)] {
+            let mut record = ScrapeRecord::from_line(&data_lines[i], ';').unwrap();
+            record.seeders = seeders;
+            record.leechers = leechers;
+            updated.insert(i, Some(record));
+        }
+        
+        let rows = changed_rows(&data_lines, &updated, |i| i == 3, ';');
+        
+        assert_eq!(rows, [format!("{};a;5;9;2;2", "a".repeat(40)), format!("{};c;5;5;2;7", "c".repeat(40))]);
+        assert_eq!(delta_csv_path(Path::new("data/torrents_part_1.csv.gz")), Path::new("data/torrents_part_1.delta.csv"));
+    }
+    
+    #[test]
+    fn test_shard() {
+        assert_eq!(Shard::parse("2/4"), Some(Shard { index: 1, count: 4 }));
+        assert_eq!(Shard::parse("0/4"), None);
+        assert_eq!(Shard::parse("5/4"), None);
+        assert_eq!(Shard::parse("abc"), None);
+        
+        // 0x00000005 % 4 == 1 -> shard 2 de 4
+        let shard = Shard::parse("2/4").unwrap();
+        assert!(shard.contains("000000052c6b6858d61da9543d4231a71db4b1c9"));
+        assert!(!shard.contains("000000042c6b6858d61da9543d4231a71db4b1c9"));
+        assert!(!shard.contains("not-a-hash"));
+        assert!(Shard::parse("1/4").unwrap().contains("not-a-hash"));
+        assert!(Shard::parse("1/4").unwrap().contains("+0000005"));
+        assert_eq!(
+            shard.output_path(Path::new("/data/torrents_part_3.csv.gz")),
+            PathBuf::from("/data/shard-2-of-4_torrents_part_3.csv.gz")
+        );
+    }
+    
+    #[test]
+    fn test_read_infohashes_skips_invalid_lines() {
+        let input = "ABCDEF0123456789ABCDEF0123456789ABCDEF01\n\nnot-a-hash\n  0123456789abcdef0123456789abcdef01234567  \n";
+        
+        let hashes = read_infohashes(input.as_bytes());
+        
+        assert_eq!(hashes, vec![
+            "abcdef0123456789abcdef0123456789abcdef01".to_string(),
+            "0123456789abcdef0123456789abcdef01234567".to_string(),
+        ]);
+    }
+}
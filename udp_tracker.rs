@@ -0,0 +1,1570 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, UdpSocket};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::csv_writer::{is_valid_infohash, join_csv_fields, normalize_infohash, split_csv_line, DEFAULT_CSV_SEP};
+use crate::dht::{dht_peer_count, DhtOpts};
+use crate::progress::ProgressCallback;
+
+// Límites por defecto del timeout adaptativo por tracker
+pub const DEFAULT_TRACKER_TIMEOUT_INITIAL_MS: u64 = 1000;
+pub const DEFAULT_TRACKER_TIMEOUT_MIN_MS: u64 = 200;
+pub const DEFAULT_TRACKER_TIMEOUT_MAX_MS: u64 = 5000;
+// El timeout se ajusta a este múltiplo de la media del RTT
+const RTT_TIMEOUT_FACTOR: u32 = 3;
+pub const MAX_HASHES_PER_SCRAPE: usize = 74;
+const CONNECT_RESPONSE_LEN: usize = 16;
+const SCRAPE_HEADER_LEN: usize = 8;
+// Bytes extra del buffer de recepción para detectar respuestas con relleno
+const RESPONSE_SLACK: usize = 64;
+const SCRAPE_ENTRY_LEN: usize = 12;
+const ANNOUNCE_REQUEST_LEN: usize = 98;
+// Cabecera de la respuesta al announce: action, transaction, interval, leechers, seeders
+const ANNOUNCE_RESPONSE_LEN: usize = 20;
+// BEP-15 da 60 s de vida al connection id; se renueva un poco antes
+pub const CONNECTION_ID_TTL: Duration = Duration::from_secs(50);
+// Timeouts seguidos tras los que se deja de contactar un tracker
+pub const DEFAULT_BREAKER_THRESHOLD: u32 = 3;
+
+// Trackers UDP públicos que se usan si no hay TRACKERS_FILE
+pub const DEFAULT_TRACKERS: &[&str] = &[
+    "tracker.opentrackr.org:1337",
+    "open.stealth.si:80",
+    "tracker.torrent.eu.org:451",
+    "exodus.desync.com:6969",
+    "tracker.moeking.me:6969",
+    "opentracker.i2p.rocks:6969",
+    "tracker.bitsearch.to:1337",
+    "tracker.tiny-vps.com:6969",
+    "tracker.openbittorrent.com:6969",
+];
+
+// Acepta host:port o una URL de announce tal cual sale de un magnet
+// (udp://host:port/announce) y devuelve el host:port para el socket
+pub fn tracker_address(entry: &str) -> Result<String, String> {
+    let Some((scheme, rest)) = entry.split_once("://") else {
+        return Ok(entry.to_string());
+    };
+    if !scheme.eq_ignore_ascii_case("udp") {
+        return Err(format!("unsupported scheme '{}', only udp:// trackers can be scraped", scheme));
+    }
+    
+    let address = rest.split('/').next().unwrap_or("");
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(address.to_string()),
+        _ => Err("missing host:port".to_string()),
+    }
+}
+
+// Opciones de un tracker en TRACKERS_FILE
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TrackerOptions {
+    // chunk=N: máximo de hashes por scrape
+    pub chunk_size: Option<usize>,
+    // announce: el tracker no contesta scrapes de clientes que no han anunciado
+    pub announce: bool,
+}
+
+// Una línea del archivo de trackers: la dirección y sus opciones
+pub fn parse_tracker_line(line: &str) -> Result<(String, TrackerOptions), String> {
+    let mut parts = line.split_whitespace();
+    let address = tracker_address(parts.next().unwrap_or(""))?;
+    let mut options = TrackerOptions::default();
+    for option in parts {
+        if option == "announce" {
+            options.announce = true;
+            continue;
+        }
+        match option.strip_prefix("chunk=").map(str::parse::<usize>) {
+            Some(Ok(size)) if size > 0 => options.chunk_size = Some(size),
+            _ => return Err(format!("invalid option '{}', expected chunk=N or announce", option)),
+        }
+    }
+    Ok((address, options))
+}
+
+// Trackers desde TRACKERS_FILE (uno por línea, '#' para comentarios) o la
+// lista por defecto, con las opciones de los que tienen alguna
+pub fn load_trackers() -> std::io::Result<(Vec<String>, HashMap<String, TrackerOptions>)> {
+    let Ok(path) = std::env::var("TRACKERS_FILE") else {
+        return Ok((DEFAULT_TRACKERS.iter().map(|t| t.to_string()).collect(), HashMap::new()));
+    };
+    Ok(parse_trackers(&std::fs::read_to_string(path)?))
+}
+
+fn is_tracker_entry(line: &str) -> bool {
+    !line.is_empty() && !line.starts_with('#')
+}
+
+// Contenido de un archivo de trackers; las líneas inválidas se avisan y se omiten
+pub fn parse_trackers(content: &str) -> (Vec<String>, HashMap<String, TrackerOptions>) {
+    let mut trackers = Vec::new();
+    let mut configured = HashMap::new();
+    for line in content.lines().map(str::trim).filter(|line| is_tracker_entry(line)) {
+        match parse_tracker_line(line) {
+            Ok((address, options)) => {
+                if options != TrackerOptions::default() {
+                    configured.insert(address.clone(), options);
+                }
+                trackers.push(address);
+            }
+            Err(e) => eprintln!("Warning: skipping tracker {}: {}", line, e),
+        }
+    }
+    (trackers, configured)
+}
+
+// Quita del archivo las líneas de los trackers en `dead`. Comentarios, líneas
+// en blanco y entradas que no se pueden interpretar se dejan como están.
+pub fn prune_tracker_lines(content: &str, dead: &HashSet<String>) -> String {
+    content
+        .lines()
+        .filter(|line| {
+            let entry = line.trim();
+            !is_tracker_entry(entry) || !matches!(parse_tracker_line(entry), Ok((address, _)) if dead.contains(&address))
+        })
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+// Semáforo contador para limitar los sockets UDP abiertos a la vez,
+// independientemente de cuántos batches y trackers se consulten a la vez
+pub struct SocketSemaphore {
+    permits: Mutex<usize>,
+    available: Condvar,
+}
+
+pub struct SocketPermit<'a> {
+    semaphore: &'a SocketSemaphore,
+}
+
+impl SocketSemaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: Mutex::new(permits.max(1)),
+            available: Condvar::new(),
+        }
+    }
+    
+    pub fn acquire(&self) -> SocketPermit<'_> {
+        let mut permits = self.permits.lock().unwrap();
+        while *permits == 0 {
+            permits = self.available.wait(permits).unwrap();
+        }
+        *permits -= 1;
+        SocketPermit { semaphore: self }
+    }
+}
+
+impl Drop for SocketPermit<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.permits.lock().unwrap() += 1;
+        self.semaphore.available.notify_one();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TorrentStats {
+    pub seeders: u32,
+    pub leechers: u32,
+    pub completed: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScrapeRecord {
+    pub infohash: String,
+    pub name: String,
+    pub size_bytes: String,
+    pub created_unix: String,
+    pub seeders: u32,
+    pub leechers: u32,
+    pub completed: String,
+    pub scraped_date: i64,
+    // Columnas adicionales (files_count, ...) que se conservan tal cual
+    pub extra: Vec<String>,
+}
+
+impl ScrapeRecord {
+    pub fn from_line(line: &str, sep: char) -> Option<Self> {
+        let parts = split_csv_line(line, sep);
+        if parts.len() < 8 {
+            return None;
+        }
+        
+        Some(Self {
+            infohash: parts[0].to_lowercase(),
+            name: parts[1].clone(),
+            size_bytes: parts[2].clone(),
+            created_unix: parts[3].clone(),
+            seeders: parts[4].parse().unwrap_or(0),
+            leechers: parts[5].parse().unwrap_or(0),
+            completed: parts[6].clone(),
+            scraped_date: parts[7].parse().unwrap_or(0),
+            extra: parts[8..].to_vec(),
+        })
+    }
+    
+    pub fn to_line(&self, sep: char) -> String {
+        let mut fields = vec![
+            self.infohash.clone(),
+            self.name.clone(),
+            self.size_bytes.clone(),
+            self.created_unix.clone(),
+            self.seeders.to_string(),
+            self.leechers.to_string(),
+            self.completed.clone(),
+            self.scraped_date.to_string(),
+        ];
+        fields.extend(self.extra.iter().cloned());
+        join_csv_fields(&fields, sep)
+    }
+    
+    // Mismo tipo que TorrentCsvRecord::peers, que escribe la misma columna
+    pub fn peers(&self) -> u64 {
+        self.seeders as u64 + self.leechers as u64
+    }
+    
+    // Cambia una columna adicional (índice en la fila completa, >= 8),
+    // rellenando con vacíos si la fila tiene menos columnas
+    pub fn set_extra(&mut self, column: usize, value: &str) {
+        let i = column.saturating_sub(8);
+        if self.extra.len() <= i {
+            self.extra.resize(i + 1, String::new());
+        }
+        self.extra[i] = value.to_string();
+    }
+}
+
+// UDP_PORT_RANGE=49152-49200: puertos de origen permitidos por el firewall de salida
+pub fn parse_port_range(value: &str) -> Option<RangeInclusive<u16>> {
+    let (start, end) = value.trim().split_once('-')?;
+    let (start, end) = (start.trim().parse::<u16>().ok()?, end.trim().parse::<u16>().ok()?);
+    (start > 0 && start <= end).then_some(start..=end)
+}
+
+// Sin rango se usa un puerto efímero; con rango se prueban los puertos en orden
+// hasta que uno esté libre
+pub fn bind_udp_socket(port_range: Option<&RangeInclusive<u16>>) -> std::io::Result<UdpSocket> {
+    let Some(range) = port_range else {
+        return UdpSocket::bind("0.0.0.0:0");
+    };
+    
+    for port in range.clone() {
+        if let Ok(socket) = UdpSocket::bind(("0.0.0.0", port)) {
+            return Ok(socket);
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AddrInUse,
+        format!("no free UDP port in {}-{}", range.start(), range.end()),
+    ))
+}
+
+// Timeout adaptativo por tracker: empieza en `initial`, se dobla tras cada
+// timeout (hasta `max`) y tras cada respuesta puede bajar hacia un múltiplo
+// de la media móvil del RTT (nunca por debajo de `min`)
+pub struct AdaptiveTimeouts {
+    initial: Duration,
+    min: Duration,
+    max: Duration,
+    state: Mutex<HashMap<String, TrackerTimeout>>,
+}
+
+#[derive(Clone, Copy)]
+struct TrackerTimeout {
+    srtt: Option<Duration>,
+    timeout: Duration,
+}
+
+impl AdaptiveTimeouts {
+    pub fn new(initial: Duration, min: Duration, max: Duration) -> Self {
+        let max = max.max(min);
+        Self {
+            initial: initial.clamp(min, max),
+            min,
+            max,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    fn entry<'a>(&self, state: &'a mut HashMap<String, TrackerTimeout>, tracker: &str) -> &'a mut TrackerTimeout {
+        state.entry(tracker.to_string()).or_insert(TrackerTimeout {
+            srtt: None,
+            timeout: self.initial,
+        })
+    }
+    
+    fn timeout_for(&self, tracker: &str) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        self.entry(&mut state, tracker).timeout
+    }
+    
+    fn record_rtt(&self, tracker: &str, rtt: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let entry = self.entry(&mut state, tracker);
+        // Media móvil exponencial con peso 1/8, como el SRTT de TCP
+        let srtt = match entry.srtt {
+            Some(srtt) => (srtt * 7 + rtt) / 8,
+            None => rtt,
+        };
+        entry.srtt = Some(srtt);
+        entry.timeout = entry.timeout.min((srtt * RTT_TIMEOUT_FACTOR).clamp(self.min, self.max));
+    }
+    
+    fn record_timeout(&self, tracker: &str) {
+        let mut state = self.state.lock().unwrap();
+        let entry = self.entry(&mut state, tracker);
+        entry.timeout = (entry.timeout * 2).min(self.max);
+    }
+}
+
+impl Default for AdaptiveTimeouts {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(DEFAULT_TRACKER_TIMEOUT_INITIAL_MS),
+            Duration::from_millis(DEFAULT_TRACKER_TIMEOUT_MIN_MS),
+            Duration::from_millis(DEFAULT_TRACKER_TIMEOUT_MAX_MS),
+        )
+    }
+}
+
+// Hashes por petición de scrape, por tracker. Parte del valor configurado
+// (o del global) y baja cuando un tracker devuelve menos entradas de las pedidas;
+// el valor efectivo se mantiene durante toda la ejecución.
+pub struct ChunkSizes {
+    default: usize,
+    state: Mutex<HashMap<String, usize>>,
+}
+
+impl ChunkSizes {
+    pub fn new(default: usize, configured: HashMap<String, usize>) -> Self {
+        let clamp = |size: usize| size.clamp(1, MAX_HASHES_PER_SCRAPE);
+        let configured = configured.into_iter().map(|(tracker, size)| (tracker, clamp(size))).collect();
+        Self {
+            default: clamp(default),
+            state: Mutex::new(configured),
+        }
+    }
+    
+    pub fn chunk_size_for(&self, tracker: &str) -> usize {
+        self.state.lock().unwrap().get(tracker).copied().unwrap_or(self.default)
+    }
+    
+    // Se queda con las entradas que el tracker sí contestó, o con la mitad si
+    // no contestó ninguna; siempre estrictamente menor que `requested`
+    fn record_short_response(&self, tracker: &str, requested: usize, answered: usize) {
+        let size = if answered > 0 { answered } else { requested / 2 };
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entry(tracker.to_string()).or_insert(self.default);
+        *entry = (*entry).min(size.max(1));
+    }
+}
+
+impl Default for ChunkSizes {
+    fn default() -> Self {
+        Self::new(MAX_HASHES_PER_SCRAPE, HashMap::new())
+    }
+}
+
+// Cortacircuitos por tracker: tras `threshold` timeouts seguidos no se le
+// vuelve a contactar en toda la ejecución o, con `cooldown`, hasta que pase
+// la espera (que se dobla cada vez que vuelve a caer). 0 lo desactiva.
+pub struct CircuitBreakers {
+    threshold: u32,
+    cooldown: Option<Duration>,
+    state: Mutex<HashMap<String, Breaker>>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_timeouts: u32,
+    open_since: Option<Instant>,
+    // Aperturas seguidas sin ninguna respuesta entre medias
+    trips: u32,
+    tripped: bool,
+}
+
+impl CircuitBreakers {
+    pub fn new(threshold: u32, cooldown: Option<Duration>) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    pub fn is_open(&self, tracker: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(breaker) = state.get_mut(tracker) else {
+            return false;
+        };
+        let Some(since) = breaker.open_since else {
+            return false;
+        };
+        let Some(cooldown) = self.cooldown else {
+            return true;
+        };
+        if since.elapsed() < cooldown.saturating_mul(1 << breaker.trips.saturating_sub(1).min(16)) {
+            return true;
+        }
+        // Pasada la espera se prueba otra vez; un solo timeout más lo vuelve a abrir
+        breaker.open_since = None;
+        breaker.consecutive_timeouts = self.threshold - 1;
+        false
+    }
+    
+    fn record_timeout(&self, tracker: &str) {
+        if self.threshold == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let breaker = state.entry(tracker.to_string()).or_default();
+        breaker.consecutive_timeouts += 1;
+        if breaker.consecutive_timeouts >= self.threshold && breaker.open_since.is_none() {
+            let timeouts = breaker.consecutive_timeouts;
+            debug_log(format_args!("{} timed out {} times in a row, skipping it", tracker, timeouts));
+            breaker.open_since = Some(Instant::now());
+            breaker.trips += 1;
+            breaker.tripped = true;
+        }
+    }
+    
+    // Otro lote puede haber abierto el corte mientras esta respuesta llegaba:
+    // una respuesta prueba que el tracker vive, así que también lo cierra
+    fn record_success(&self, tracker: &str) {
+        if let Some(breaker) = self.state.lock().unwrap().get_mut(tracker) {
+            breaker.consecutive_timeouts = 0;
+            breaker.open_since = None;
+            breaker.trips = 0;
+        }
+    }
+    
+    // Trackers que se cortaron en algún momento de la ejecución, ordenados
+    pub fn tripped(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let mut trackers: Vec<String> = state
+            .iter()
+            .filter(|(_, breaker)| breaker.tripped)
+            .map(|(tracker, _)| tracker.clone())
+            .collect();
+        trackers.sort();
+        trackers
+    }
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self::new(DEFAULT_BREAKER_THRESHOLD, None)
+    }
+}
+
+// Opciones de red por socket: puertos de origen y timeouts por tracker
+#[derive(Clone, Default)]
+pub struct NetOpts {
+    pub port_range: Option<RangeInclusive<u16>>,
+    pub timeouts: Arc<AdaptiveTimeouts>,
+    pub chunk_sizes: Arc<ChunkSizes>,
+    // Direcciones ya resueltas (DoH); los trackers que no estén usan el DNS del sistema
+    pub resolved: Arc<HashMap<String, SocketAddr>>,
+    // Vida del connection id antes de repetir el connect (None = CONNECTION_ID_TTL)
+    pub connection_ttl: Option<Duration>,
+    // Compartido entre lotes: un tracker caído no se reintenta en cada uno
+    pub breakers: Arc<CircuitBreakers>,
+    // Origen de los transaction id, y del peer id y la key del announce
+    // (None = aleatorios); los tests lo fijan para comprobar los paquetes byte a byte
+    pub transaction_ids: Option<TransactionIds>,
+    // Trackers a los que hay que anunciarse antes del scrape
+    pub announce_first: Arc<HashSet<String>>,
+    // A true, los scrapes en curso paran antes del siguiente chunk (y sueltan
+    // su socket); el scraper lo usa al agotarse el tiempo de un archivo
+    pub cancel: Arc<AtomicBool>,
+}
+
+// RefUnwindSafe: cada tracker se scrapea dentro de catch_unwind
+pub type TransactionIds = Arc<dyn Fn() -> u32 + Send + Sync + std::panic::RefUnwindSafe>;
+
+impl NetOpts {
+    fn next_transaction_id(&self) -> u32 {
+        match &self.transaction_ids {
+            Some(next) => next(),
+            None => rand::random(),
+        }
+    }
+}
+
+// Diagnóstico de bajo nivel, solo con DEBUG=1
+fn debug_log(message: std::fmt::Arguments) {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    if *ENABLED.get_or_init(|| std::env::var("DEBUG").is_ok_and(|v| v == "1")) {
+        eprintln!("[debug] {}", message);
+    }
+}
+
+// Envía una petición y espera la respuesta con el timeout actual del tracker,
+// registrando el RTT o el timeout para ajustar las siguientes
+fn request(socket: &UdpSocket, tracker: &str, net: &NetOpts, req: &[u8], buf: &mut [u8]) -> std::io::Result<usize> {
+    socket.set_read_timeout(Some(net.timeouts.timeout_for(tracker)))?;
+    let started = Instant::now();
+    socket.send(req)?;
+    
+    match socket.recv(buf) {
+        Ok(n) => {
+            net.timeouts.record_rtt(tracker, started.elapsed());
+            net.breakers.record_success(tracker);
+            Ok(n)
+        }
+        Err(e) => {
+            if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) {
+                net.timeouts.record_timeout(tracker);
+                net.breakers.record_timeout(tracker);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Protocolo UDP Tracker
+// 1. Connect request: abre un socket hacia el tracker y devuelve el connection id
+pub fn connect_tracker(tracker: &str, net: &NetOpts) -> std::io::Result<(UdpSocket, u64)> {
+    let socket = bind_udp_socket(net.port_range.as_ref())?;
+    match net.resolved.get(tracker) {
+        Some(addr) => socket.connect(addr)?,
+        None => socket.connect(tracker)?,
+    }
+    
+    let transaction_id = net.next_transaction_id();
+    let mut connect_req = Vec::new();
+    connect_req.extend_from_slice(&0x41727101980u64.to_be_bytes());
+    connect_req.extend_from_slice(&0u32.to_be_bytes());
+    connect_req.extend_from_slice(&transaction_id.to_be_bytes());
+    
+    // Hueco de sobra para trackers que rellenan la respuesta; solo cuentan los 16 primeros bytes
+    let mut buf = [0u8; CONNECT_RESPONSE_LEN + RESPONSE_SLACK];
+    let n = request(&socket, tracker, net, &connect_req, &mut buf)?;
+    if n != CONNECT_RESPONSE_LEN {
+        debug_log(format_args!("connect response from {} has {} bytes, expected {}", tracker, n, CONNECT_RESPONSE_LEN));
+    }
+    if n < CONNECT_RESPONSE_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "short connect response",
+        ));
+    }
+    
+    let recv_action = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let recv_trans = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    if recv_action != 0 || recv_trans != transaction_id {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "unexpected connect response",
+        ));
+    }
+    
+    let connection_id = u64::from_be_bytes([buf[8], buf[9], buf[10], buf[11], buf[12], buf[13], buf[14], buf[15]]);
+    Ok((socket, connection_id))
+}
+
+// Announce mínimo (BEP-15, action 1) para trackers que solo contestan scrapes
+// de clientes que ya han anunciado. Va con event=stopped y num_want=0 para
+// no sumarse a los seeders/leechers que se van a leer ni pedir peers.
+fn announce_tracker(
+    socket: &UdpSocket,
+    connection_id: u64,
+    tracker: &str,
+    net: &NetOpts,
+    infohash: &[u8],
+) -> std::io::Result<()> {
+    let transaction_id = net.next_transaction_id();
+    let mut peer_id = b"-TS0001-".to_vec();
+    peer_id.extend((0..3).flat_map(|_| net.next_transaction_id().to_be_bytes()));
+    let port = socket.local_addr()?.port();
+    
+    let mut announce_req = Vec::with_capacity(ANNOUNCE_REQUEST_LEN);
+    announce_req.extend_from_slice(&connection_id.to_be_bytes());
+    announce_req.extend_from_slice(&1u32.to_be_bytes());
+    announce_req.extend_from_slice(&transaction_id.to_be_bytes());
+    announce_req.extend_from_slice(infohash);
+    announce_req.extend_from_slice(&peer_id);
+    announce_req.extend_from_slice(&0u64.to_be_bytes()); // downloaded
+    announce_req.extend_from_slice(&0u64.to_be_bytes()); // left
+    announce_req.extend_from_slice(&0u64.to_be_bytes()); // uploaded
+    announce_req.extend_from_slice(&3u32.to_be_bytes()); // event: stopped
+    announce_req.extend_from_slice(&0u32.to_be_bytes()); // IP: la de origen
+    announce_req.extend_from_slice(&net.next_transaction_id().to_be_bytes()); // key
+    announce_req.extend_from_slice(&0i32.to_be_bytes()); // num_want
+    announce_req.extend_from_slice(&port.to_be_bytes());
+    
+    let mut buf = [0u8; ANNOUNCE_RESPONSE_LEN + RESPONSE_SLACK];
+    let n = request(socket, tracker, net, &announce_req, &mut buf)?;
+    let recv_action = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let recv_trans = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    if n < ANNOUNCE_RESPONSE_LEN || recv_action != 1 || recv_trans != transaction_id {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "unexpected announce response",
+        ));
+    }
+    Ok(())
+}
+
+// Protocolo UDP Tracker
+pub fn scrape_udp_tracker(
+    tracker: &str,
+    infohashes: &[Vec<u8>],
+    net: &NetOpts,
+) -> HashMap<String, TorrentStats> {
+    let mut results = HashMap::new();
+    let ttl = net.connection_ttl.unwrap_or(CONNECTION_ID_TTL);
+    let mut connection: Option<(UdpSocket, u64, Instant)> = None;
+    
+    // 2. Scrape request, en chunks del tamaño actual del tracker (74 como
+    // máximo, límite BEP-15). Los hashes sin respuesta se reenvían en el siguiente.
+    let mut pending = infohashes;
+    while !pending.is_empty() {
+        if net.cancel.load(Ordering::SeqCst) || net.breakers.is_open(tracker) {
+            break;
+        }
+        // Scrapes largos: el connection id caduca y hay que repetir el handshake
+        if connection.as_ref().is_none_or(|(_, _, since)| since.elapsed() >= ttl) {
+            if connection.is_some() {
+                debug_log(format_args!("connection id for {} expired, reconnecting", tracker));
+            }
+            let connected_at = Instant::now();
+            match connect_tracker(tracker, net) {
+                Ok((socket, connection_id)) => {
+                    // Si el announce falla se intenta el scrape igualmente
+                    if net.announce_first.contains(tracker) {
+                        if let Err(e) = announce_tracker(&socket, connection_id, tracker, net, &pending[0]) {
+                            debug_log(format_args!("announce to {} failed: {}", tracker, e));
+                        }
+                    }
+                    connection = Some((socket, connection_id, connected_at));
+                }
+                Err(_) => break,
+            }
+        }
+        let Some((socket, connection_id, _)) = &connection else {
+            break;
+        };
+        
+        let chunk = &pending[..net.chunk_sizes.chunk_size_for(tracker).min(pending.len())];
+        let scrape_trans_id = net.next_transaction_id();
+        let mut scrape_req = Vec::new();
+        scrape_req.extend_from_slice(&connection_id.to_be_bytes());
+        scrape_req.extend_from_slice(&2u32.to_be_bytes());
+        scrape_req.extend_from_slice(&scrape_trans_id.to_be_bytes());
+        
+        for hash in chunk {
+            scrape_req.extend_from_slice(hash);
+        }
+        
+        // 3. Leer respuesta: cabecera de 8 bytes + 12 bytes por hash
+        let expected = SCRAPE_HEADER_LEN + SCRAPE_ENTRY_LEN * chunk.len();
+        let mut response = vec![0u8; expected + RESPONSE_SLACK];
+        let n = match request(socket, tracker, net, &scrape_req, &mut response) {
+            Ok(n) => n,
+            Err(_) => break,
+        };
+        if n < SCRAPE_HEADER_LEN {
+            debug_log(format_args!("scrape response from {} too short: {} bytes", tracker, n));
+            break;
+        }
+        // El relleno al final se ignora; una respuesta corta se trata más abajo
+        if n > expected {
+            debug_log(format_args!("scrape response from {} has {} bytes, expected {}", tracker, n, expected));
+        }
+        let n = n.min(expected);
+        
+        let recv_action = u32::from_be_bytes([response[0], response[1], response[2], response[3]]);
+        let recv_trans = u32::from_be_bytes([response[4], response[5], response[6], response[7]]);
+        
+        if recv_trans != scrape_trans_id {
+            break;
+        }
+        
+        // Action 3: el tracker devuelve un mensaje de error
+        if recv_action == 3 {
+            let message = String::from_utf8_lossy(&response[8..n]);
+            eprintln!("Tracker {} returned error: {}", tracker, message);
+            break;
+        }
+        
+        if recv_action != 2 {
+            break;
+        }
+        
+        let answered = ((n - SCRAPE_HEADER_LEN) / SCRAPE_ENTRY_LEN).min(chunk.len());
+        let mut offset = SCRAPE_HEADER_LEN;
+        for hash in &chunk[..answered] {
+            let read_u32 = |at: usize| {
+                u32::from_be_bytes([response[at], response[at + 1], response[at + 2], response[at + 3]])
+            };
+            let seeders = read_u32(offset);
+            let completed = read_u32(offset + 4);
+            let leechers = read_u32(offset + 8);
+            
+            let hash_str = hex::encode(hash).to_lowercase();
+            results.insert(hash_str, TorrentStats { seeders, leechers, completed });
+            
+            offset += SCRAPE_ENTRY_LEN;
+        }
+        
+        // Respuesta corta: el tracker no admite chunks tan grandes
+        if answered < chunk.len() {
+            if chunk.len() == 1 {
+                break;
+            }
+            net.chunk_sizes.record_short_response(tracker, chunk.len(), answered);
+        }
+        pending = &pending[answered..];
+    }
+    
+    results
+}
+
+pub fn scrape_all_trackers_parallel(
+    infohashes: &[Vec<u8>],
+    opts: &ScrapeOpts,
+) -> Vec<HashMap<String, TorrentStats>> {
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let mut handles = vec![];
+    
+    for tracker in opts.trackers.iter() {
+        let tracker = tracker.to_string();
+        let infohashes = infohashes.to_vec();
+        let results = Arc::clone(&results);
+        let socket_limit = Arc::clone(&opts.socket_limit);
+        let net = opts.net.clone();
+        
+        let handle = thread::spawn(move || {
+            let _permit = socket_limit.acquire();
+            if let Ok(tracker_results) = std::panic::catch_unwind(|| {
+                scrape_udp_tracker(&tracker, &infohashes, &net)
+            }) {
+                if !tracker_results.is_empty() {
+                    results.lock().unwrap().push(tracker_results);
+                }
+            }
+        });
+        
+        handles.push(handle);
+    }
+    
+    // Esperar a que todos los threads terminen
+    for handle in handles {
+        let _ = handle.join();
+    }
+    
+    let final_results = results.lock().unwrap().clone();
+    final_results
+}
+
+// Configuración compartida por todos los batches
+#[derive(Clone)]
+pub struct ScrapeOpts {
+    pub trackers: Arc<Vec<String>>,
+    pub socket_limit: Arc<SocketSemaphore>,
+    pub net: NetOpts,
+    // Separador de campos del CSV (CSV_SEP)
+    pub csv_sep: char,
+    // Si es false se conserva el completed del CSV (UPDATE_COMPLETED=0)
+    pub update_completed: bool,
+    // Búsqueda en el DHT para los que los trackers dan por muertos (USE_DHT=1)
+    pub dht: Option<Arc<DhtOpts>>,
+    // Recibe el progreso tras cada tanda de batches
+    pub on_progress: ProgressCallback,
+}
+
+pub enum TrackerHealth {
+    Ok(Duration),
+    Timeout,
+    Error(String),
+}
+
+pub fn check_tracker(tracker: &str, net: &NetOpts) -> TrackerHealth {
+    let started = std::time::Instant::now();
+    
+    match connect_tracker(tracker, net) {
+        Ok(_) => TrackerHealth::Ok(started.elapsed()),
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+            TrackerHealth::Timeout
+        }
+        Err(e) => TrackerHealth::Error(e.to_string()),
+    }
+}
+
+// Handshake connect con todos los trackers a la vez, en el orden de entrada
+pub fn probe_trackers(trackers: &[String], net: &NetOpts) -> Vec<TrackerHealth> {
+    let handles: Vec<_> = trackers
+        .iter()
+        .map(|tracker| {
+            let tracker = tracker.clone();
+            let net = net.clone();
+            thread::spawn(move || check_tracker(&tracker, &net))
+        })
+        .collect();
+    
+    handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap_or_else(|_| TrackerHealth::Error("panicked".to_string())))
+        .collect()
+}
+
+pub fn count_alive_trackers(trackers: &[String], net: &NetOpts) -> usize {
+    probe_trackers(trackers, net)
+        .iter()
+        .filter(|health| matches!(health, TrackerHealth::Ok(_)))
+        .count()
+}
+
+// Resultado de consultar una fila del CSV
+pub struct RowResult {
+    pub idx: usize,
+    // None si el torrent está muerto y debe eliminarse
+    pub record: Option<ScrapeRecord>,
+    // Estadísticas consolidadas, solo si algún tracker respondió
+    pub scraped: Option<TorrentStats>,
+    // Seeders que tenía la fila antes de esta consulta
+    pub previous_seeders: Option<u32>,
+}
+
+// Consulta todos los trackers y se queda con el máximo de cada contador por
+// infohash. Los hashes que ningún tracker contestó no aparecen en el mapa.
+pub fn best_stats(hash_bytes: &[Vec<u8>], opts: &ScrapeOpts) -> HashMap<String, TorrentStats> {
+    let mut best: HashMap<String, TorrentStats> = HashMap::new();
+    
+    for tracker_result in scrape_all_trackers_parallel(hash_bytes, opts) {
+        for (hash, stats) in tracker_result {
+            best.entry(hash)
+                .and_modify(|b| {
+                    b.seeders = b.seeders.max(stats.seeders);
+                    b.leechers = b.leechers.max(stats.leechers);
+                    b.completed = b.completed.max(stats.completed);
+                })
+                .or_insert(stats);
+        }
+    }
+    
+    best
+}
+
+// Scrape de una lista cualquiera de infohashes v1 en todos los trackers de
+// `opts`, sin CSV de por medio. El troceado en chunks, los reintentos de los
+// hashes sin respuesta y el máximo entre trackers van por debajo. El mapa va
+// por infohash normalizado; los no válidos y los que nadie contesta no aparecen.
+pub fn scrape_infohashes(hashes: &[String], opts: &ScrapeOpts) -> HashMap<String, TorrentStats> {
+    let mut seen = HashSet::new();
+    let hash_bytes: Vec<Vec<u8>> = hashes
+        .iter()
+        .filter_map(|hash| normalize_infohash(hash).filter(|h| is_valid_infohash(h)))
+        .filter(|hash| seen.insert(hash.clone()))
+        .filter_map(|hash| hex::decode(hash).ok())
+        .collect();
+    if hash_bytes.is_empty() {
+        return HashMap::new();
+    }
+    
+    best_stats(&hash_bytes, opts)
+}
+
+// Estado actual de un único infohash v1 (40 hex) en todos los trackers a la
+// vez, con el máximo de cada campo. None si el hash no es válido o ningún
+// tracker contesta.
+pub fn scrape_single(infohash: &str, trackers: &[String]) -> Option<TorrentStats> {
+    let hash = normalize_infohash(infohash).filter(|h| is_valid_infohash(h))?;
+    let opts = ScrapeOpts {
+        trackers: Arc::new(trackers.to_vec()),
+        socket_limit: Arc::new(SocketSemaphore::new(trackers.len())),
+        net: NetOpts::default(),
+        csv_sep: DEFAULT_CSV_SEP,
+        update_completed: true,
+        dht: None,
+        on_progress: Arc::new(|_| {}),
+    };
+    
+    scrape_infohashes(std::slice::from_ref(&hash), &opts).remove(&hash)
+}
+
+// Búsquedas DHT en paralelo, cada una con un socket del límite compartido
+fn dht_peer_counts(hashes: &[&str], dht: &DhtOpts, socket_limit: &SocketSemaphore) -> HashMap<String, usize> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = hashes
+            .iter()
+            .filter_map(|hash| Some((*hash, hex::decode(hash).ok()?)))
+            .map(|(hash, bytes)| {
+                scope.spawn(move || {
+                    let _permit = socket_limit.acquire();
+                    (hash.to_string(), dht_peer_count(&bytes, dht).unwrap_or(0))
+                })
+            })
+            .collect();
+        handles.into_iter().filter_map(|handle| handle.join().ok()).collect()
+    })
+}
+
+pub fn process_batch(
+    batch_indices: Vec<usize>,
+    batch_hashes: Vec<String>,
+    data_lines: &[String],
+    opts: &ScrapeOpts,
+) -> Vec<RowResult> {
+    let hash_bytes: Vec<Vec<u8>> = batch_hashes
+        .iter()
+        .filter_map(|h| hex::decode(h).ok())
+        .filter(|b| b.len() == 20)
+        .collect();
+    
+    if hash_bytes.is_empty() {
+        return batch_indices
+            .iter()
+            .map(|&idx| RowResult {
+                idx,
+                record: ScrapeRecord::from_line(&data_lines[idx], opts.csv_sep),
+                scraped: None,
+                previous_seeders: None,
+            })
+            .collect();
+    }
+    
+    // Consultar todos los trackers EN PARALELO
+    let best = scrape_infohashes(&batch_hashes, opts);
+    
+    // Con USE_DHT, los que los trackers dan por muertos se buscan en el DHT
+    let dht_peers = match &opts.dht {
+        Some(dht) => {
+            let dead: Vec<&str> = batch_hashes
+                .iter()
+                .map(|h| h.as_str())
+                .filter(|h| best.get(&h.to_lowercase()).is_some_and(|s| s.seeders == 0 && s.leechers == 0))
+                .collect();
+            dht_peer_counts(&dead, dht, &opts.socket_limit)
+        }
+        None => HashMap::new(),
+    };
+    
+    // Consolidar resultados
+    let mut final_results = Vec::new();
+    
+    for (i, &line_idx) in batch_indices.iter().enumerate() {
+        if i >= batch_hashes.len() {
+            break;
+        }
+        
+        let scraped = best.get(&batch_hashes[i].to_lowercase()).cloned();
+        
+        // Leer el valor anterior antes de sobreescribirlo, para el delta
+        let original = ScrapeRecord::from_line(&data_lines[line_idx], opts.csv_sep);
+        let previous_seeders = original.as_ref().map(|r| r.seeders);
+        
+        match &scraped {
+            None => {
+                if let Some(record) = original {
+                    final_results.push(RowResult { idx: line_idx, record: Some(record), scraped, previous_seeders });
+                }
+            }
+            Some(stats) if stats.seeders > 0 || stats.leechers > 0 => {
+                if let Some(mut record) = original {
+                    record.seeders = stats.seeders;
+                    record.leechers = stats.leechers;
+                    if opts.update_completed {
+                        record.completed = stats.completed.to_string();
+                    }
+                    record.scraped_date = chrono::Utc::now().timestamp();
+                    final_results.push(RowResult { idx: line_idx, record: Some(record), scraped, previous_seeders });
+                }
+            }
+            // Vivo solo en el DHT: no se sabe cuántos peers son seeders, así que
+            // la estimación va a leechers y seeders queda a 0
+            Some(_) if dht_peers.get(&batch_hashes[i]).is_some_and(|&peers| peers > 0) => {
+                if let Some(mut record) = original {
+                    record.seeders = 0;
+                    record.leechers = dht_peers[&batch_hashes[i]] as u32;
+                    record.scraped_date = chrono::Utc::now().timestamp();
+                    final_results.push(RowResult { idx: line_idx, record: Some(record), scraped, previous_seeders });
+                }
+            }
+            Some(_) => final_results.push(RowResult { idx: line_idx, record: None, scraped, previous_seeders }),
+        }
+    }
+    
+    final_results
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // Tracker UDP de prueba que implementa el handshake connect + scrape de BEP-15
+    struct MockTracker {
+        addr: String,
+        handle: thread::JoinHandle<usize>,
+    }
+    
+    const MOCK_CONNECTION_ID: u64 = 0x1122334455667788;
+    
+    fn spawn_mock_tracker(stats: HashMap<Vec<u8>, (u32, u32, u32)>, error_on_scrape: bool) -> MockTracker {
+        spawn_truncating_mock_tracker(stats, error_on_scrape, usize::MAX)
+    }
+    
+    // Como spawn_mock_tracker, pero contesta como mucho `max_entries` hashes por scrape
+    fn spawn_truncating_mock_tracker(
+        stats: HashMap<Vec<u8>, (u32, u32, u32)>,
+        error_on_scrape: bool,
+        max_entries: usize,
+    ) -> MockTracker {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        
+        let handle = thread::spawn(move || {
+            let mut scrapes = 0;
+            let mut buf = [0u8; 2048];
+            
+            while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+                let action = u32::from_be_bytes(buf[8..12].try_into().unwrap());
+                let trans = &buf[12..16];
+                let mut reply = Vec::new();
+                
+                match action {
+                    0 if n == 16 => {
+                        reply.extend_from_slice(&0u32.to_be_bytes());
+                        reply.extend_from_slice(trans);
+                        reply.extend_from_slice(&MOCK_CONNECTION_ID.to_be_bytes());
+                    }
+                    2 if error_on_scrape => {
+                        reply.extend_from_slice(&3u32.to_be_bytes());
+                        reply.extend_from_slice(trans);
+                        reply.extend_from_slice(b"scrape not allowed");
+                    }
+                    2 => {
+                        assert_eq!(u64::from_be_bytes(buf[0..8].try_into().unwrap()), MOCK_CONNECTION_ID);
+                        assert!((n - 16) / 20 <= MAX_HASHES_PER_SCRAPE);
+                        scrapes += 1;
+                        
+                        reply.extend_from_slice(&2u32.to_be_bytes());
+                        reply.extend_from_slice(trans);
+                        for hash in buf[16..n].chunks(20).take(max_entries) {
+                            let (seeders, completed, leechers) = stats.get(hash).copied().unwrap_or((0, 0, 0));
+                            reply.extend_from_slice(&seeders.to_be_bytes());
+                            reply.extend_from_slice(&completed.to_be_bytes());
+                            reply.extend_from_slice(&leechers.to_be_bytes());
+                        }
+                    }
+                    _ => continue,
+                }
+                
+                socket.send_to(&reply, peer).unwrap();
+            }
+            
+            scrapes
+        });
+        
+        MockTracker { addr, handle }
+    }
+    
+    fn test_hashes(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| vec![i as u8; 20]).collect()
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_single_chunk() {
+        let hashes = test_hashes(3);
+        let stats = hashes.iter()
+            .enumerate()
+            .map(|(i, h)| (h.clone(), (10 + i as u32, 100 + i as u32, 20 + i as u32)))
+            .collect();
+        let tracker = spawn_mock_tracker(stats, false);
+        
+        let results = scrape_udp_tracker(&tracker.addr, &hashes, &NetOpts::default());
+        
+        assert_eq!(results.len(), 3);
+        let second = &results[&hex::encode(&hashes[1])];
+        assert_eq!(second.seeders, 11);
+        assert_eq!(second.completed, 101);
+        assert_eq!(second.leechers, 21);
+        assert_eq!(tracker.handle.join().unwrap(), 1);
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_reconnects_when_connection_id_expires() {
+        // Cada connect da un id nuevo y el scrape solo acepta el último
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut connects = 0u64;
+            let mut buf = [0u8; 2048];
+            while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+                let action = u32::from_be_bytes(buf[8..12].try_into().unwrap());
+                let mut reply = Vec::new();
+                if action == 0 {
+                    connects += 1;
+                    reply.extend_from_slice(&0u32.to_be_bytes());
+                    reply.extend_from_slice(&buf[12..16]);
+                    reply.extend_from_slice(&connects.to_be_bytes());
+                } else if u64::from_be_bytes(buf[0..8].try_into().unwrap()) != connects {
+                    reply.extend_from_slice(&3u32.to_be_bytes());
+                    reply.extend_from_slice(&buf[12..16]);
+                    reply.extend_from_slice(b"connection id expired");
+                } else {
+                    reply.extend_from_slice(&2u32.to_be_bytes());
+                    reply.extend_from_slice(&buf[12..16]);
+                    reply.resize(8 + (n - 16) / 20 * SCRAPE_ENTRY_LEN, 0);
+                }
+                socket.send_to(&reply, peer).unwrap();
+            }
+            connects
+        });
+        
+        let hashes = test_hashes(5);
+        let net = NetOpts {
+            chunk_sizes: Arc::new(ChunkSizes::new(2, HashMap::new())),
+            connection_ttl: Some(Duration::ZERO),
+            ..NetOpts::default()
+        };
+        
+        let results = scrape_udp_tracker(&addr, &hashes, &net);
+        
+        assert_eq!(results.len(), 5);
+        // Tres chunks (2 + 2 + 1), un connect antes de cada uno
+        assert_eq!(handle.join().unwrap(), 3);
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_full_chunk() {
+        let hashes = test_hashes(MAX_HASHES_PER_SCRAPE);
+        let stats = hashes.iter()
+            .enumerate()
+            .map(|(i, h)| (h.clone(), (1000 + i as u32, 2000 + i as u32, 3000 + i as u32)))
+            .collect();
+        let tracker = spawn_mock_tracker(stats, false);
+        
+        let results = scrape_udp_tracker(&tracker.addr, &hashes, &NetOpts::default());
+        
+        assert_eq!(results.len(), MAX_HASHES_PER_SCRAPE);
+        for (i, hash) in hashes.iter().enumerate() {
+            let stats = &results[&hex::encode(hash)];
+            assert_eq!((stats.seeders, stats.completed, stats.leechers), (1000 + i as u32, 2000 + i as u32, 3000 + i as u32));
+        }
+        assert_eq!(tracker.handle.join().unwrap(), 1);
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_multi_chunk() {
+        let hashes = test_hashes(100);
+        let stats = hashes.iter()
+            .enumerate()
+            .map(|(i, h)| (h.clone(), (i as u32, 0, 1)))
+            .collect();
+        let tracker = spawn_mock_tracker(stats, false);
+        
+        let results = scrape_udp_tracker(&tracker.addr, &hashes, &NetOpts::default());
+        
+        assert_eq!(results.len(), 100);
+        assert_eq!(results[&hex::encode(&hashes[99])].seeders, 99);
+        assert_eq!(tracker.handle.join().unwrap(), 2);
+    }
+    
+    #[test]
+    fn test_connect_tracker_accepts_padded_response() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            // Primero una respuesta con relleno y después una demasiado corta
+            for padding in [4usize, 0] {
+                let (_, peer) = socket.recv_from(&mut buf).unwrap();
+                let mut reply = 0u32.to_be_bytes().to_vec();
+                reply.extend_from_slice(&buf[12..16]);
+                if padding > 0 {
+                    reply.extend_from_slice(&MOCK_CONNECTION_ID.to_be_bytes());
+                    reply.resize(reply.len() + padding, 0);
+                }
+                socket.send_to(&reply, peer).unwrap();
+            }
+        });
+        
+        let (_, connection_id) = connect_tracker(&addr, &NetOpts::default()).unwrap();
+        assert_eq!(connection_id, MOCK_CONNECTION_ID);
+        let err = connect_tracker(&addr, &NetOpts::default()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        handle.join().unwrap();
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_shrinks_chunk_on_short_response() {
+        let hashes = test_hashes(100);
+        let stats = hashes.iter()
+            .enumerate()
+            .map(|(i, h)| (h.clone(), (i as u32, 0, 1)))
+            .collect();
+        let tracker = spawn_truncating_mock_tracker(stats, false, 30);
+        let net = NetOpts::default();
+        
+        let results = scrape_udp_tracker(&tracker.addr, &hashes, &net);
+        
+        // 74 pedidos -> 30 contestados; después 30, 30 y 10
+        assert_eq!(results.len(), 100);
+        assert_eq!(results[&hex::encode(&hashes[99])].seeders, 99);
+        assert_eq!(net.chunk_sizes.chunk_size_for(&tracker.addr), 30);
+        assert_eq!(tracker.handle.join().unwrap(), 4);
+        
+        let configured = ChunkSizes::new(MAX_HASHES_PER_SCRAPE, HashMap::from([("strict:1337".to_string(), 500)]));
+        assert_eq!(configured.chunk_size_for("strict:1337"), MAX_HASHES_PER_SCRAPE);
+        configured.record_short_response("strict:1337", 74, 0);
+        assert_eq!(configured.chunk_size_for("strict:1337"), 37);
+    }
+    
+    #[test]
+    fn test_best_stats_takes_max_across_trackers() {
+        let hashes = test_hashes(2);
+        let first = spawn_mock_tracker(HashMap::from([(hashes[0].clone(), (10, 5, 1))]), false);
+        let second = spawn_mock_tracker(HashMap::from([(hashes[0].clone(), (3, 50, 7))]), false);
+        let opts = ScrapeOpts {
+            trackers: Arc::new(vec![first.addr.clone(), second.addr.clone()]),
+            socket_limit: Arc::new(SocketSemaphore::new(2)),
+            net: NetOpts::default(),
+            csv_sep: ';',
+            update_completed: true,
+            dht: None,
+            on_progress: Arc::new(|_| {}),
+        };
+        
+        let best = best_stats(&hashes, &opts);
+        
+        let stats = &best[&hex::encode(&hashes[0])];
+        assert_eq!((stats.seeders, stats.completed, stats.leechers), (10, 50, 7));
+        assert_eq!(best[&hex::encode(&hashes[1])].seeders, 0);
+    }
+    
+    #[test]
+    fn test_tracker_address() {
+        assert_eq!(tracker_address("tracker.example:1337").unwrap(), "tracker.example:1337");
+        assert_eq!(tracker_address("udp://tracker.example:1337/announce").unwrap(), "tracker.example:1337");
+        assert_eq!(tracker_address("UDP://tracker.example:6969").unwrap(), "tracker.example:6969");
+        assert!(tracker_address("http://tracker.example:80/announce").is_err());
+        assert!(tracker_address("udp://tracker.example/announce").is_err());
+        
+        assert_eq!(
+            parse_tracker_line("udp://tracker.example:1337/announce  chunk=20").unwrap(),
+            ("tracker.example:1337".to_string(), TrackerOptions { chunk_size: Some(20), announce: false })
+        );
+        assert_eq!(parse_tracker_line("tracker.example:1337").unwrap().1, TrackerOptions::default());
+        assert!(parse_tracker_line("tracker.example:1337 announce").unwrap().1.announce);
+        assert!(parse_tracker_line("tracker.example:1337 chunk=0").is_err());
+    }
+    
+    #[test]
+    fn test_scrape_single() {
+        let hashes = test_hashes(1);
+        let first = spawn_mock_tracker(HashMap::from([(hashes[0].clone(), (4, 9, 1))]), false);
+        let second = spawn_mock_tracker(HashMap::from([(hashes[0].clone(), (6, 2, 3))]), false);
+        let trackers = vec![first.addr.clone(), second.addr.clone()];
+        
+        let stats = scrape_single(&hex::encode(&hashes[0]).to_uppercase(), &trackers).unwrap();
+        
+        assert_eq!((stats.seeders, stats.completed, stats.leechers), (6, 9, 3));
+        assert!(scrape_single("not-a-hash", &trackers).is_none());
+    }
+    
+    #[test]
+    fn test_scrape_infohashes() {
+        let hashes = test_hashes(2);
+        let first_stats = HashMap::from([(hashes[0].clone(), (4, 9, 1)), (hashes[1].clone(), (1, 1, 1))]);
+        let first = spawn_mock_tracker(first_stats, false);
+        let second = spawn_mock_tracker(HashMap::from([(hashes[0].clone(), (6, 2, 3))]), false);
+        let opts = ScrapeOpts {
+            trackers: Arc::new(vec![first.addr.clone(), second.addr.clone()]),
+            socket_limit: Arc::new(SocketSemaphore::new(2)),
+            net: NetOpts::default(),
+            csv_sep: ';',
+            update_completed: true,
+            dht: None,
+            on_progress: Arc::new(|_| {}),
+        };
+        let input = vec![
+            hex::encode_upper(&hashes[0]),
+            hex::encode(&hashes[0]),
+            hex::encode(&hashes[1]),
+            "not-a-hash".to_string(),
+        ];
+        
+        let results = scrape_infohashes(&input, &opts);
+        
+        assert_eq!(results.len(), 2);
+        let stats = &results[&hex::encode(&hashes[0])];
+        assert_eq!((stats.seeders, stats.completed, stats.leechers), (6, 9, 3));
+        // Un solo scrape por tracker aunque el hash llegue repetido
+        assert_eq!(first.handle.join().unwrap(), 1);
+        assert!(scrape_infohashes(&["zz".to_string()], &opts).is_empty());
+    }
+    
+    #[test]
+    fn test_process_batch_update_completed_policy() {
+        let hashes = test_hashes(1);
+        let hash = hex::encode(&hashes[0]);
+        let tracker = spawn_mock_tracker(HashMap::from([(hashes[0].clone(), (10, 999, 2))]), false);
+        let lines = vec![format!("{};Name;1024;1700000000;1;1;5;1700000100;3", hash)];
+        let mut opts = ScrapeOpts {
+            trackers: Arc::new(vec![tracker.addr.clone()]),
+            socket_limit: Arc::new(SocketSemaphore::new(1)),
+            net: NetOpts::default(),
+            csv_sep: ';',
+            update_completed: true,
+            dht: None,
+            on_progress: Arc::new(|_| {}),
+        };
+        
+        let updated = process_batch(vec![0], vec![hash.clone()], &lines, &opts);
+        let record = updated[0].record.as_ref().unwrap();
+        assert_eq!((record.seeders, record.completed.as_str()), (10, "999"));
+        
+        opts.update_completed = false;
+        let kept = process_batch(vec![0], vec![hash], &lines, &opts);
+        let record = kept[0].record.as_ref().unwrap();
+        assert_eq!((record.seeders, record.completed.as_str()), (10, "5"));
+    }
+    
+    #[test]
+    fn test_adaptive_timeouts() {
+        let timeouts = AdaptiveTimeouts::new(
+            Duration::from_millis(1000),
+            Duration::from_millis(200),
+            Duration::from_millis(5000),
+        );
+        assert_eq!(timeouts.timeout_for("slow"), Duration::from_millis(1000));
+        
+        // Solo crece tras un timeout, y nunca por encima del máximo
+        timeouts.record_timeout("slow");
+        assert_eq!(timeouts.timeout_for("slow"), Duration::from_millis(2000));
+        for _ in 0..5 {
+            timeouts.record_timeout("slow");
+        }
+        assert_eq!(timeouts.timeout_for("slow"), Duration::from_millis(5000));
+        
+        // Un tracker rápido baja hacia 3x su RTT, con el mínimo como suelo
+        timeouts.record_rtt("fast", Duration::from_millis(100));
+        assert_eq!(timeouts.timeout_for("fast"), Duration::from_millis(300));
+        timeouts.record_rtt("fast", Duration::from_millis(10));
+        assert!(timeouts.timeout_for("fast") >= Duration::from_millis(200));
+        
+        // Un RTT alto no sube el timeout por sí solo
+        timeouts.record_rtt("fast", Duration::from_millis(900));
+        assert!(timeouts.timeout_for("fast") <= Duration::from_millis(300));
+        assert_eq!(timeouts.timeout_for("slow"), Duration::from_millis(5000));
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_packet_format() {
+        // Guarda cada paquete recibido y contesta sin estadísticas
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut packets = Vec::new();
+            let mut buf = [0u8; 2048];
+            while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+                let action = u32::from_be_bytes(buf[8..12].try_into().unwrap());
+                let mut reply = Vec::new();
+                reply.extend_from_slice(&action.to_be_bytes());
+                reply.extend_from_slice(&buf[12..16]);
+                match action {
+                    0 => reply.extend_from_slice(&MOCK_CONNECTION_ID.to_be_bytes()),
+                    1 => reply.resize(ANNOUNCE_RESPONSE_LEN, 0),
+                    _ => reply.resize(SCRAPE_HEADER_LEN + (n - 16) / 20 * SCRAPE_ENTRY_LEN, 0),
+                }
+                packets.push(buf[..n].to_vec());
+                socket.send_to(&reply, peer).unwrap();
+            }
+            packets
+        });
+        
+        let counter = Arc::new(std::sync::atomic::AtomicU32::new(0x0a0b0c00));
+        let net = NetOpts {
+            transaction_ids: Some(Arc::new(move || counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst))),
+            announce_first: Arc::new(HashSet::from([addr.clone()])),
+            ..NetOpts::default()
+        };
+        let hashes = test_hashes(2);
+        
+        assert_eq!(scrape_udp_tracker(&addr, &hashes, &net).len(), 2);
+        
+        let packets = handle.join().unwrap();
+        let mut connect = vec![0, 0, 0x04, 0x17, 0x27, 0x10, 0x19, 0x80, 0, 0, 0, 0];
+        connect.extend_from_slice(&[0x0a, 0x0b, 0x0c, 0x00]);
+        // Transaction id, peer id (3 ids tras "-TS0001-") y key salen del mismo contador
+        let mut announce = MOCK_CONNECTION_ID.to_be_bytes().to_vec();
+        announce.extend_from_slice(&[0, 0, 0, 1, 0x0a, 0x0b, 0x0c, 0x01]);
+        announce.extend_from_slice(&[0u8; 20]);
+        announce.extend_from_slice(b"-TS0001-");
+        announce.extend_from_slice(&[0x0a, 0x0b, 0x0c, 0x02, 0x0a, 0x0b, 0x0c, 0x03, 0x0a, 0x0b, 0x0c, 0x04]);
+        announce.extend_from_slice(&[0u8; 24]);
+        announce.extend_from_slice(&[0, 0, 0, 3, 0, 0, 0, 0, 0x0a, 0x0b, 0x0c, 0x05, 0, 0, 0, 0]);
+        let mut scrape = MOCK_CONNECTION_ID.to_be_bytes().to_vec();
+        scrape.extend_from_slice(&[0, 0, 0, 2, 0x0a, 0x0b, 0x0c, 0x06]);
+        scrape.extend_from_slice(&[0u8; 20]);
+        scrape.extend_from_slice(&[1u8; 20]);
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0], connect);
+        // Los dos últimos bytes son el puerto local del cliente
+        assert_eq!(packets[1].len(), ANNOUNCE_REQUEST_LEN);
+        assert_eq!(packets[1][..ANNOUNCE_REQUEST_LEN - 2], announce[..]);
+        assert_eq!(packets[2], scrape);
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_announces_first() {
+        // Tracker estricto: rechaza el scrape hasta recibir un announce
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut announces = Vec::new();
+            let mut buf = [0u8; 2048];
+            while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+                let action = u32::from_be_bytes(buf[8..12].try_into().unwrap());
+                let mut reply = Vec::new();
+                reply.extend_from_slice(&action.to_be_bytes());
+                reply.extend_from_slice(&buf[12..16]);
+                match action {
+                    0 => reply.extend_from_slice(&MOCK_CONNECTION_ID.to_be_bytes()),
+                    1 => {
+                        assert_eq!(n, ANNOUNCE_REQUEST_LEN);
+                        announces.push(buf[16..36].to_vec());
+                        reply.extend_from_slice(&[0u8; 12]);
+                    }
+                    _ if announces.is_empty() => {
+                        reply[..4].copy_from_slice(&3u32.to_be_bytes());
+                        reply.extend_from_slice(b"announce first");
+                    }
+                    _ => reply.resize(SCRAPE_HEADER_LEN + (n - 16) / 20 * SCRAPE_ENTRY_LEN, 0),
+                }
+                socket.send_to(&reply, peer).unwrap();
+            }
+            announces
+        });
+        let hashes = test_hashes(2);
+        
+        assert!(scrape_udp_tracker(&addr, &hashes, &NetOpts::default()).is_empty());
+        let net = NetOpts {
+            announce_first: Arc::new(HashSet::from([addr.clone()])),
+            ..NetOpts::default()
+        };
+        assert_eq!(scrape_udp_tracker(&addr, &hashes, &net).len(), 2);
+        
+        net.cancel.store(true, Ordering::SeqCst);
+        assert!(scrape_udp_tracker(&addr, &hashes, &net).is_empty());
+        assert_eq!(handle.join().unwrap(), [hashes[0].clone()]);
+    }
+    
+    #[test]
+    fn test_prune_tracker_lines() {
+        let content = "# públicos\nudp://dead.example:6969/announce\nalive.example:1337 chunk=20\n\nnot a tracker\ndead.example:6969\n";
+        let dead = HashSet::from(["dead.example:6969".to_string()]);
+
+        assert_eq!(prune_tracker_lines(content, &dead), "# públicos\nalive.example:1337 chunk=20\n\nnot a tracker\n");
+        assert_eq!(prune_tracker_lines("a.example:1", &HashSet::new()), "a.example:1\n");
+    }
+
+    #[test]
+    fn test_circuit_breakers() {
+        let breakers = CircuitBreakers::new(2, None);
+        breakers.record_timeout("a");
+        breakers.record_success("a");
+        breakers.record_timeout("a");
+        assert!(!breakers.is_open("a"));
+        breakers.record_timeout("a");
+        assert!(breakers.is_open("a"));
+        assert_eq!(breakers.tripped(), ["a"]);
+        
+        // Con cooldown se reintenta al pasar la espera, y esta se dobla si vuelve a fallar
+        let breakers = CircuitBreakers::new(1, Some(Duration::from_millis(30)));
+        breakers.record_timeout("b");
+        assert!(breakers.is_open("b"));
+        thread::sleep(Duration::from_millis(40));
+        assert!(!breakers.is_open("b"));
+        breakers.record_timeout("b");
+        thread::sleep(Duration::from_millis(40));
+        assert!(breakers.is_open("b"));
+        
+        // Una respuesta que llega con el corte ya abierto lo cierra sin romper el estado
+        breakers.record_success("b");
+        assert!(!breakers.is_open("b"));
+        breakers.record_timeout("b");
+        assert!(breakers.is_open("b"));
+        assert_eq!(breakers.tripped(), ["b"]);
+        
+        // Un tracker cortado ya no se contacta: el segundo scrape no espera al timeout
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tracker = silent.local_addr().unwrap().to_string();
+        let net = NetOpts {
+            timeouts: Arc::new(AdaptiveTimeouts::new(
+                Duration::from_millis(200),
+                Duration::from_millis(200),
+                Duration::from_millis(200),
+            )),
+            breakers: Arc::new(CircuitBreakers::new(1, None)),
+            ..NetOpts::default()
+        };
+        assert!(scrape_udp_tracker(&tracker, &test_hashes(1), &net).is_empty());
+        let started = Instant::now();
+        assert!(scrape_udp_tracker(&tracker, &test_hashes(1), &net).is_empty());
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+    
+    #[test]
+    fn test_count_alive_trackers() {
+        let alive = spawn_mock_tracker(HashMap::new(), false);
+        // Socket que nunca responde
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let trackers = vec![alive.addr.clone(), silent.local_addr().unwrap().to_string()];
+        let net = NetOpts {
+            timeouts: Arc::new(AdaptiveTimeouts::new(
+                Duration::from_millis(200),
+                Duration::from_millis(200),
+                Duration::from_millis(200),
+            )),
+            ..NetOpts::default()
+        };
+        
+        assert_eq!(count_alive_trackers(&trackers, &net), 1);
+    }
+    
+    #[test]
+    fn test_parse_port_range() {
+        assert_eq!(parse_port_range("49152-49200"), Some(49152..=49200));
+        assert_eq!(parse_port_range(" 50000 - 50000 "), Some(50000..=50000));
+        assert_eq!(parse_port_range("49200-49152"), None);
+        assert_eq!(parse_port_range("0-10"), None);
+        assert_eq!(parse_port_range("49152"), None);
+        assert_eq!(parse_port_range("a-b"), None);
+    }
+    
+    #[test]
+    fn test_bind_udp_socket_skips_ports_in_use() {
+        let taken = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        
+        assert!(bind_udp_socket(Some(&(port..=port))).is_err());
+        // El siguiente puerto del rango puede estar ocupado por otro proceso,
+        // así que solo se comprueba que no reutiliza el ocupado
+        if let Ok(socket) = bind_udp_socket(Some(&(port..=port.saturating_add(20)))) {
+            assert_ne!(socket.local_addr().unwrap().port(), port);
+        }
+    }
+    
+    #[test]
+    fn test_scrape_udp_tracker_error_action() {
+        let tracker = spawn_mock_tracker(HashMap::new(), true);
+        
+        let results = scrape_udp_tracker(&tracker.addr, &test_hashes(2), &NetOpts::default());
+        
+        assert!(results.is_empty());
+        assert_eq!(tracker.handle.join().unwrap(), 0);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const DEFAULT_USER_AGENT: &str = DEFAULT_USER_AGENTS[0];
+
+// User-Agents de navegadores actuales; se usan en orden, uno por petición
+const DEFAULT_USER_AGENTS: [&str; 5] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+];
+
+// User-Agent más cabeceras extra que se envían juntos en una petición
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderProfile {
+    pub user_agent: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+// Rota entre perfiles de cabeceras; el contador es atómico para poder
+// compartirla entre tareas
+#[derive(Debug)]
+pub struct HeaderRotation {
+    profiles: Vec<HeaderProfile>,
+    next: AtomicUsize,
+}
+
+impl HeaderRotation {
+    pub fn new(profiles: Vec<HeaderProfile>) -> Self {
+        let profiles = if profiles.is_empty() {
+            Self::default().profiles
+        } else {
+            profiles
+        };
+        Self { profiles, next: AtomicUsize::new(0) }
+    }
+
+    // Acepta un JSON con una lista de perfiles o un texto con un User-Agent
+    // por línea ('#' para comentarios)
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+
+        let profiles = if content.trim_start().starts_with('[') {
+            serde_json::from_str(&content)?
+        } else {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|ua| HeaderProfile { user_agent: ua.to_string(), headers: HashMap::new() })
+                .collect()
+        };
+
+        Ok(Self::new(profiles))
+    }
+
+    pub fn next_profile(&self) -> &HeaderProfile {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        &self.profiles[i % self.profiles.len()]
+    }
+}
+
+impl Default for HeaderRotation {
+    fn default() -> Self {
+        let profiles = DEFAULT_USER_AGENTS
+            .iter()
+            .map(|ua| HeaderProfile { user_agent: ua.to_string(), headers: HashMap::new() })
+            .collect();
+        Self { profiles, next: AtomicUsize::new(0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_cycles_through_profiles() {
+        let rotation = HeaderRotation::new(vec![
+            HeaderProfile { user_agent: "a".to_string(), headers: HashMap::new() },
+            HeaderProfile { user_agent: "b".to_string(), headers: HashMap::new() },
+        ]);
+
+        let seen: Vec<&str> = (0..5).map(|_| rotation.next_profile().user_agent.as_str()).collect();
+        assert_eq!(seen, ["a", "b", "a", "b", "a"]);
+        assert_eq!(HeaderRotation::new(Vec::new()).next_profile().user_agent, DEFAULT_USER_AGENTS[0]);
+    }
+}